@@ -1,4 +1,6 @@
 use client::Client;
+use std::fmt::{self, Write};
+use tracing_core::{Field, Level, LevelFilter};
 use tracing_subscriber::fmt::format::DefaultFields;
 use tracing_subscriber::fmt::FormatFields;
 
@@ -50,6 +52,10 @@ pub trait Config {
     /// Note that enabling callstack collection can and will introduce a non-trivial overhead at
     /// every instrumentation point. Specifying 0 frames will disable stack trace collection.
     ///
+    /// Individual spans can override this with an integer `tracy.callstack_depth` field, e.g.
+    /// `info_span!("suspicious", tracy.callstack_depth = 16)`, which allows for collecting deep
+    /// callstacks for just a few spans. The depth is clamped to the maximum supported by Tracy.
+    ///
     /// Default implementation returns `0`.
     fn stack_depth(&self, metadata: &tracing_core::Metadata<'_>) -> u16 {
         let _ = metadata;
@@ -68,6 +74,322 @@ pub trait Config {
         true
     }
 
+    /// Specify whether to skip formatting the span fields while no profiler is connected.
+    ///
+    /// Formatting the fields of every span is wasted work when nothing is going to receive them,
+    /// which is most of the time with the `ondemand` feature enabled. When enabled, the fields are
+    /// only formatted if [`Client::is_connected`] at the time the span is created or its values are
+    /// recorded. The zones are still emitted either way, but the spans created while disconnected
+    /// will be missing their fields, even if a profiler connects while they are still open.
+    ///
+    /// Default implementation returns `false`.
+    fn lazy_text(&self) -> bool {
+        false
+    }
+
+    /// Specify whether the span described by `metadata` should be represented as a Tracy zone.
+    ///
+    /// Spans for which this returns `false` are skipped entirely when they are entered and
+    /// exited. This method is called on every span entry and exit, so it should be cheap and must
+    /// return the same result for the same `metadata` every time. See also the [per-layer
+    /// filtering] support in `tracing-subscriber`, which provides similar functionality.
+    ///
+    /// Default implementation returns `true`.
+    ///
+    /// [per-layer filtering]: tracing_subscriber::layer#per-layer-filtering
+    fn span_filter(&self, metadata: &tracing_core::Metadata<'_>) -> bool {
+        let _ = metadata;
+        true
+    }
+
+    /// Specify the least severe level of the spans that should be represented as Tracy zones.
+    ///
+    /// Spans more verbose than this level are skipped in the same way as the spans rejected by
+    /// [`Config::span_filter`]. This is a cheaper way to limit the profiling overhead to the
+    /// important spans than a filter, and does not affect the events.
+    ///
+    /// Default implementation returns [`LevelFilter::TRACE`], representing all spans.
+    fn min_level(&self) -> LevelFilter {
+        LevelFilter::TRACE
+    }
+
+    /// Specify the color of the Tracy zone for the span described by `metadata`.
+    ///
+    /// The colour shall be provided as RGBA, where the least significant 8 bits represent the
+    /// alpha component and most significant 8 bits represent the red component. Returning `None`
+    /// leaves the zone with the color chosen by the profiler. [`level_color`] provides a mapping
+    /// that highlights warnings and errors.
+    ///
+    /// Default implementation returns `None`.
+    fn span_color(&self, metadata: &tracing_core::Metadata<'_>) -> Option<u32> {
+        let _ = metadata;
+        None
+    }
+
+    /// Specify the color of the Tracy message for the event described by `metadata`.
+    ///
+    /// The color follows the same convention as [`Config::span_color`]. Returning `None` emits a
+    /// message without a specific color. [`level_color`] provides a mapping that highlights
+    /// warnings and errors.
+    ///
+    /// Default implementation returns `None`.
+    fn message_color(&self, metadata: &tracing_core::Metadata<'_>) -> Option<u32> {
+        let _ = metadata;
+        None
+    }
+
+    /// Specify whether tracing spans should be represented as Tracy fibers.
+    ///
+    /// By default the zones are tracked on a per-thread stack, which requires that a span is
+    /// exited on the same thread it was entered on. When this returns `true`, every span is
+    /// instead given its own fiber and its zones are recorded on that fiber. This allows a span to
+    /// be entered on one thread and exited on another (as may happen in async executors) while
+    /// still producing a single continuous zone, at the cost of displaying each span on a
+    /// separate fiber track in the profiler. Events are still recorded on the thread.
+    ///
+    /// Has no effect unless the `fibers` feature is enabled.
+    ///
+    /// Default implementation returns `false`.
+    fn fibers(&self) -> bool {
+        false
+    }
+
+    /// Specify whether numeric event fields should be plotted.
+    ///
+    /// When this returns `true`, every integer and floating point field of an event is added as a
+    /// point to the Tracy plot named after the field, in addition to being included in the
+    /// message. Repeated events with the same field thus build up a time series, e.g.
+    /// `tracing::info!(queue_depth = 42)` adds a point to the `queue_depth` plot.
+    ///
+    /// Default implementation returns `false`.
+    fn auto_plot(&self) -> bool {
+        false
+    }
+
+    /// Specify whether threads should be named in Tracy after their Rust thread names.
+    ///
+    /// When this returns `true`, the first span entered on a thread names the thread in the
+    /// profiler after [`std::thread::Thread::name`]. Threads without a name are left alone.
+    ///
+    /// Default implementation returns `false`.
+    fn thread_naming(&self) -> bool {
+        false
+    }
+
+    /// Specify whether the name of the thread entering a span should be added to its zone text.
+    ///
+    /// This makes it possible to tell which thread produced a zone when looking at it in
+    /// isolation. Threads without a name are identified by their [`std::thread::ThreadId`].
+    ///
+    /// Default implementation returns `false`.
+    fn thread_name_in_zone(&self) -> bool {
+        false
+    }
+
+    /// Specify whether the zone names should include the target and the location of the spans.
+    ///
+    /// When this returns `true`, zones are named like `module::path::span_name @ file.rs:42`
+    /// rather than just `span_name`, which makes it possible to tell apart the spans with the
+    /// same name and quickly find them in the code. The fields are included after the name, as
+    /// usual. This makes the names of the spans with fields more expensive to construct.
+    ///
+    /// Default implementation returns `false`.
+    fn verbose_names(&self) -> bool {
+        false
+    }
+
+    /// Specify whether the name of the parent of a span should be added to its zone text.
+    ///
+    /// Tracy nests zones according to the timeline of a thread, which does not reflect explicit
+    /// span parents, e.g. for spans created on one thread and entered on another. With this
+    /// enabled, every zone of a span with a parent carries a `parent = <parent name>` note that
+    /// allows for reconstructing the logical span tree. Zones of root spans get no such note.
+    ///
+    /// Default implementation returns `false`.
+    fn parent_annotation(&self) -> bool {
+        false
+    }
+
+    /// Specify whether the layer should describe the Tracy client in the trace.
+    ///
+    /// When enabled, the [`client::feature_summary`] is added to the application information of
+    /// the trace once the layer is added to a subscriber. This reveals e.g. whether sampling was
+    /// enabled to anybody looking at the trace later. Nothing is added if the client has not been
+    /// started yet by then, see [`TracyLayer::new_manual`](super::TracyLayer::new_manual).
+    ///
+    /// Default implementation returns `false`.
+    fn startup_info(&self) -> bool {
+        false
+    }
+
+    /// Provide the image of the frame that has just ended.
+    ///
+    /// This is called right after the layer marks the end of a frame for a `tracy.frame_mark`
+    /// field, which pairs every frame with its screenshot in the profiler. The image is returned
+    /// as an RGBA buffer with its width and height, with the same requirements as for
+    /// [`Client::frame_image`]. Images with a buffer not matching their dimensions, or with
+    /// dimensions which are not non-zero multiples of 4, are dropped and reported via
+    /// [`Config::on_error`].
+    ///
+    /// Default implementation returns `None`.
+    fn frame_image(&self) -> Option<(Vec<u8>, u16, u16)> {
+        None
+    }
+
+    /// Specify whether the allocations made while a span is entered should be attributed to a
+    /// memory pool named after the span.
+    ///
+    /// This sets the [`Client::set_thread_memory_pool`] of the thread to the pool named after the
+    /// innermost entered span, and resets it once all spans have been exited. It only affects the
+    /// allocations made through a [`client::ProfiledAllocator::with_thread_pools`], which is
+    /// expected to be the global allocator. Spans entered as fibers are not considered.
+    ///
+    /// Default implementation returns `false`.
+    fn span_memory_pools(&self) -> bool {
+        false
+    }
+
+    /// Specify whether events should be able to record memory (de)allocations.
+    ///
+    /// When this returns `true`, the following event fields are reserved and, rather than being
+    /// included in the message, are reported to the memory view of the profiler, same as the
+    /// allocations made through a [`client::ProfiledAllocator`]:
+    ///
+    /// * `tracy.alloc_ptr` and `tracy.alloc_bytes` – an allocation of the given number of bytes,
+    ///   identified by the given address;
+    /// * `tracy.free_ptr` – the deallocation of the allocation identified by the given address;
+    /// * `tracy.alloc_pool` – the name of the memory pool of the allocation or deallocation, which
+    ///   must be the same for both. Optional.
+    ///
+    /// The addresses only identify the allocations and may be any unique integer. This allows for
+    /// bringing manually accounted memory into the profiler without a global allocator. Events
+    /// with values that do not fit into a `usize` are reported to [`Config::on_error`] instead.
+    ///
+    /// ```
+    /// tracing::trace!(tracy.alloc_pool = "textures", tracy.alloc_ptr = 1, tracy.alloc_bytes = 1024);
+    /// tracing::trace!(tracy.alloc_pool = "textures", tracy.free_ptr = 1);
+    /// ```
+    ///
+    /// Default implementation returns `false`.
+    fn memory_events(&self) -> bool {
+        false
+    }
+
+    /// Specify whether events should be marked on the timeline with a zone at their source location.
+    ///
+    /// Messages carry no source location, so the profiler can not navigate to the source of an
+    /// event. When this returns `true`, each event additionally begins and immediately ends a zone
+    /// named and located after the event, which can be used to do so. This is considerably more
+    /// expensive than the message alone. Events without a known file or line are located at
+    /// `<not available>` or line 0 respectively, same as the spans.
+    ///
+    /// Default implementation returns `false`.
+    fn event_zones(&self) -> bool {
+        false
+    }
+
+    /// Specify whether event messages should be prefixed with the source location of the event.
+    ///
+    /// When this returns `true`, messages are prefixed with the `file:line` of the event (or just
+    /// the file if the line is not known), which makes it possible to tell where a message
+    /// originated from. Events without a known file are not prefixed.
+    ///
+    /// Default implementation returns `false`.
+    fn event_location(&self) -> bool {
+        false
+    }
+
+    /// Specify whether the durations of spans should be plotted.
+    ///
+    /// When this returns `true`, the total time a span has spent entered is added, in seconds, to
+    /// the Tracy plot named after the span when the span is closed. This shows how the duration of
+    /// a particular span changes over time. Spans that were never entered are not plotted.
+    ///
+    /// Default implementation returns `false`.
+    fn duration_plots(&self) -> bool {
+        false
+    }
+
+    /// Format a field of an event into the Tracy message for the event.
+    ///
+    /// The fields are separated by commas. A field is omitted from the message if this method
+    /// does not write anything to `dest`. The reserved `tracy.frame_mark` field is handled before
+    /// and never passed to this method.
+    ///
+    /// Default implementation writes `name = value`, where the value is formatted with its
+    /// [`Debug`](std::fmt::Debug) implementation (or as-is for strings).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::fmt::Write;
+    /// use tracing_subscriber::fmt::format::DefaultFields;
+    ///
+    /// #[derive(Default)]
+    /// struct ValuesOnly(DefaultFields);
+    /// impl tracing_tracy::Config for ValuesOnly {
+    ///     type Formatter = DefaultFields;
+    ///     fn formatter(&self) -> &Self::Formatter {
+    ///         &self.0
+    ///     }
+    ///     fn format_event_field(
+    ///         &self,
+    ///         dest: &mut String,
+    ///         field: &tracing::field::Field,
+    ///         value: &dyn std::fmt::Debug,
+    ///     ) {
+    ///         // Omit the request ids and the field names.
+    ///         if field.name() != "request_id" {
+    ///             let _ = write!(dest, "{value:?}");
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn format_event_field(&self, dest: &mut String, field: &Field, value: &dyn fmt::Debug) {
+        let _ = write!(dest, "{} = {value:?}", field.name());
+    }
+
+    /// Specify whether recording new span field values should emit a Tracy message.
+    ///
+    /// When enabled, each `Span::record` call emits a message of the form
+    /// `span_name: field = value`, marking the moment of the update within the zone of a
+    /// long-lived span. The fields are formatted with [`Config::format_event_field`].
+    ///
+    /// Default implementation returns `false`.
+    fn record_messages(&self) -> bool {
+        false
+    }
+
+    /// Specify what to do with event messages that are too long for Tracy.
+    ///
+    /// Default implementation returns [`LongMessageMode::Truncate`].
+    fn long_message_mode(&self) -> LongMessageMode {
+        LongMessageMode::Truncate
+    }
+
+    /// Specify the maximum number of event messages emitted per second on each thread.
+    ///
+    /// Tracy struggles with large numbers of messages. Events in excess of the limit do not emit
+    /// messages, and once the following second starts, a single message reporting the number of
+    /// the dropped messages is emitted instead. Other effects of the events, such as frame marks
+    /// and plots, are not limited.
+    ///
+    /// Default implementation returns `None`, which does not limit the messages.
+    fn message_rate_limit(&self) -> Option<u32> {
+        None
+    }
+
+    /// Specify the color of the messages reporting errors detected by the
+    /// [`TracyLayer`](super::TracyLayer), such as spans exiting out of order.
+    ///
+    /// The colour shall be provided as RGBA, same as [`Config::message_color`]. This is only used
+    /// by the default implementation of [`Config::on_error`].
+    ///
+    /// Default implementation returns red, the color of the `ERROR` level in [`level_color`].
+    fn diagnostic_color(&self) -> u32 {
+        0xFF0000FF
+    }
+
     /// Apply handling for errors detected by the [`TracyLayer`](super::TracyLayer).
     ///
     /// Fundamentally the way the tracing crate and the Tracy profiler work are somewhat
@@ -83,21 +405,73 @@ pub trait Config {
     /// straightforward, and it is when `tracing_tracy` will invoke this method to enable users to
     /// report the issues in whatever way they wish to.
     ///
-    /// By default a message coloured in red is emitted to the tracy client.
+    /// By default a message coloured with [`Config::diagnostic_color`] is emitted to the tracy
+    /// client. The message carries a callstack, which points at the code that caused the problem
+    /// (e.g. the code exiting a span out of order).
     fn on_error(&self, client: &Client, error: &'static str) {
-        client.color_message(error, 0xFF000000, 0);
+        client.color_message_with_callstack(error, self.diagnostic_color(), 32);
     }
 }
 
+/// A color for data with the given `level` that makes warnings and errors stand out.
+///
+/// `ERROR` is mapped to red and `WARN` to orange. Other levels have no specific color.
+///
+/// # Examples
+///
+/// ```
+/// # use tracing_subscriber::fmt::format::DefaultFields;
+/// struct ColorfulConfig(DefaultFields);
+/// impl tracing_tracy::Config for ColorfulConfig {
+///     type Formatter = DefaultFields;
+///     fn formatter(&self) -> &Self::Formatter {
+///         &self.0
+///     }
+///     fn span_color(&self, metadata: &tracing::Metadata) -> Option<u32> {
+///         tracing_tracy::level_color(metadata.level())
+///     }
+/// }
+/// ```
+#[must_use]
+pub fn level_color(level: &Level) -> Option<u32> {
+    match *level {
+        Level::ERROR => Some(0xFF0000FF),
+        Level::WARN => Some(0xFFA500FF),
+        _ => None,
+    }
+}
+
+/// The handling of event messages longer than Tracy can represent in a single message.
+///
+/// See [`Config::long_message_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LongMessageMode {
+    /// Drop the end of the message, reporting the truncation via [`Config::on_error`].
+    #[default]
+    Truncate,
+    /// Emit the message as multiple consecutive messages.
+    Split,
+}
+
 /// A default configuration of the [`TracyLayer`](super::TracyLayer).
 ///
-/// This type does not allow for any adjustment of the configuration. In order to customize
-/// the behaviour of the layer implement the [`Config`] trait for your own type.
-#[derive(Default)]
-pub struct DefaultConfig(DefaultFields);
+/// This type only allows for adjusting the field formatter (see
+/// [`TracyLayer::with_formatter`](super::TracyLayer::with_formatter)). In order to customize
+/// the behaviour of the layer further implement the [`Config`] trait for your own type.
+pub struct DefaultConfig<F = DefaultFields>(pub(crate) F);
+
+// Not derived, so that `DefaultConfig::default()` does not need the formatter type spelled out.
+impl Default for DefaultConfig {
+    fn default() -> Self {
+        Self(DefaultFields::default())
+    }
+}
 
-impl Config for DefaultConfig {
-    type Formatter = DefaultFields;
+impl<F> Config for DefaultConfig<F>
+where
+    F: for<'writer> FormatFields<'writer> + 'static,
+{
+    type Formatter = F;
     fn formatter(&self) -> &Self::Formatter {
         &self.0
     }
@@ -6,13 +6,16 @@
 //! Note, however that Tracy is ultimately a profiling, not an observability, tool. As thus, some
 //! of tracing concepts cannot be represented well by Tracy. For instance, out-of-order span
 //! entries and exits, are not supported, and neither are spans that are entered and exited on
-//! different threads. This crate will attempt to mitigate the problems and retain trace validity
-//! at the cost of potentially invalid data. When such a mitigation occurs, trace will contain a
-//! message with a note about the problem.
+//! different threads (unless spans are represented as fibers, see [`Config::fibers`]). This crate
+//! will attempt to mitigate the problems and retain trace validity at the cost of potentially
+//! invalid data. When such a mitigation occurs, trace will contain a message with a note about the
+//! problem.
 //!
 //! Some other caveats to keep in mind:
 //!
-//! * Only span entries and exits are recorded;
+//! * Only span entries and exits are recorded. Every entry of a span begins a separate zone,
+//! which ends once the span is exited, so e.g. an asynchronous span polled 5 times shows up as
+//! 5 zones covering just the time the span was busy;
 //! * Events show up as messages in Tracy, however Tracy can struggle with large numbers of
 //! messages;
 //! * Some additional functionality such as plotting and memory allocation profiling is only
@@ -46,18 +49,26 @@
 //! The following crate features are provided to customize the functionality of the Tracy client:
 //!
 #![doc = include_str!("../FEATURES.mkd")]
+//!
+//! Additionally, the `log` feature provides a `TracyLogger`, which forwards
+//! the records of the `log` crate to Tracy.
 #![cfg_attr(tracing_tracy_docs, feature(doc_auto_cfg))]
 
-use client::{Client, Span};
-pub use config::{Config, DefaultConfig};
+use client::{Client, FiberName, PlotName, PoolName, Span, SpanLocation};
+pub use config::{level_color, Config, DefaultConfig, LongMessageMode};
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError, RwLock};
+use std::time::{Duration, Instant};
 use std::{fmt::Write, mem};
 use tracing_core::{
+    callsite::Identifier,
     field::{Field, Visit},
     span::{Attributes, Id, Record},
-    Event, Subscriber,
+    Event, Metadata, Subscriber,
 };
-use tracing_subscriber::fmt::format::FormatFields;
+use tracing_subscriber::fmt::format::{FormatFields, Writer};
 use tracing_subscriber::{
     layer::{Context, Layer},
     registry,
@@ -66,12 +77,295 @@ use utils::{StrCache, StrCacheGuard, VecCell};
 
 pub use client;
 mod config;
+#[cfg(feature = "log")]
+mod logger;
+#[cfg(feature = "log")]
+pub use logger::TracyLogger;
 
 type TracyFields<C> = tracing_subscriber::fmt::FormattedFields<<C as Config>::Formatter>;
 
 thread_local! {
     /// A stack of spans currently active on the current thread.
     static TRACY_SPAN_STACK: VecCell<(Span, u64)> = const { VecCell::new() };
+    /// Whether the current thread has been considered for naming by [`Config::thread_naming`].
+    static THREAD_NAMED: Cell<bool> = const { Cell::new(false) };
+    /// Event messages emitted on the current thread, for [`Config::message_rate_limit`].
+    static MESSAGE_BUDGET: Cell<MessageBudget> = const { Cell::new(MessageBudget::new()) };
+}
+
+/// The start of the coarse timestamps used for rate limiting the messages.
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Clone, Copy)]
+struct MessageBudget {
+    /// The second since [`EPOCH`] during which the messages below were counted.
+    second: u64,
+    sent: u32,
+    dropped: u64,
+}
+
+impl MessageBudget {
+    const fn new() -> Self {
+        Self {
+            second: 0,
+            sent: 0,
+            dropped: 0,
+        }
+    }
+}
+
+/// Source locations of the zones, interned per span callsite.
+///
+/// Like the callsites themselves, the locations live for the remainder of the program.
+static LOCATIONS: OnceLock<RwLock<HashMap<(Identifier, bool), &'static SpanLocation>>> =
+    OnceLock::new();
+
+/// The interned source location of a span, stored in its extensions.
+struct ZoneLocation(&'static SpanLocation);
+
+/// The location of the zones of the spans from the callsite of `metadata`, named with
+/// [`write_zone_name`].
+fn zone_location(metadata: &'static Metadata<'static>, verbose: bool) -> &'static SpanLocation {
+    let locations = LOCATIONS.get_or_init(RwLock::default);
+    let callsite = (metadata.callsite(), verbose);
+    let location = locations
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&callsite)
+        .copied();
+    location.unwrap_or_else(|| {
+        *locations
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(callsite)
+            .or_insert_with(|| {
+                let mut name = String::new();
+                write_zone_name(&mut name, metadata, None, verbose);
+                let (file, line) = source_file_line(metadata);
+                SpanLocation::new_leak(
+                    Some(name),
+                    metadata.target().to_owned(),
+                    file.to_owned(),
+                    line,
+                )
+            })
+    })
+}
+
+/// The file and line of the source location of the zones for `metadata`, with placeholders for
+/// the unknown ones.
+fn source_file_line<'a>(metadata: &Metadata<'a>) -> (&'a str, u32) {
+    let file = metadata.file().unwrap_or("<not available>");
+    (file, metadata.line().unwrap_or(0))
+}
+
+/// Write the name of the zones of the span described by `metadata`, with its `fields` if they
+/// should be included.
+///
+/// The `verbose` names are qualified with the target and suffixed with the location of the span,
+/// see [`Config::verbose_names`].
+fn write_zone_name(
+    dest: &mut String,
+    metadata: &Metadata<'_>,
+    fields: Option<&str>,
+    verbose: bool,
+) {
+    if verbose {
+        let _ = write!(dest, "{}::", metadata.target());
+    }
+    dest.push_str(metadata.name());
+    if let Some(fields) = fields {
+        let _ = write!(dest, "{{{fields}}}");
+    }
+    if verbose {
+        match (metadata.file(), metadata.line()) {
+            (Some(file), Some(line)) => {
+                let _ = write!(dest, " @ {file}:{line}");
+            }
+            (Some(file), None) => {
+                let _ = write!(dest, " @ {file}");
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+/// Plots of the numeric event fields and span durations, by name.
+static PLOTS: OnceLock<RwLock<HashMap<&'static str, PlotName>>> = OnceLock::new();
+
+fn plot_name(name: &'static str) -> PlotName {
+    let plots = PLOTS.get_or_init(RwLock::default);
+    let plot = plots
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(name)
+        .copied();
+    plot.unwrap_or_else(|| {
+        *plots
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name)
+            .or_insert_with(|| PlotName::new_leak(name.to_owned()))
+    })
+}
+
+/// Memory pools named by the `tracy.alloc_pool` event field or after spans, by name.
+static POOLS: OnceLock<RwLock<HashMap<String, PoolName>>> = OnceLock::new();
+
+fn pool_name(name: &str) -> PoolName {
+    let pools = POOLS.get_or_init(RwLock::default);
+    let pool = pools
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(name)
+        .copied();
+    pool.unwrap_or_else(|| {
+        *pools
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name.to_owned())
+            .or_insert_with(|| PoolName::new_leak(name.to_owned()))
+    })
+}
+
+/// The reserved memory fields of an event, when [`Config::memory_events`] is enabled.
+#[derive(Default)]
+struct MemoryEvent {
+    pool: Option<PoolName>,
+    alloc_ptr: Option<u64>,
+    alloc_bytes: Option<u64>,
+    free_ptr: Option<u64>,
+}
+
+impl MemoryEvent {
+    /// Store `value` if `field` is one of the reserved numeric memory fields.
+    fn record(&mut self, field: &Field, value: u64) -> bool {
+        let slot = match field.name() {
+            "tracy.alloc_ptr" => &mut self.alloc_ptr,
+            "tracy.alloc_bytes" => &mut self.alloc_bytes,
+            "tracy.free_ptr" => &mut self.free_ptr,
+            _ => return false,
+        };
+        *slot = Some(value);
+        true
+    }
+}
+
+/// The time a span has spent entered, when [`Config::duration_plots`] is enabled.
+#[derive(Default)]
+struct BusyTime {
+    entered_at: Option<Instant>,
+    depth: usize,
+    busy: Option<Duration>,
+}
+
+/// The name of the zones of a span with fields, when [`Config::format_fields_in_zone_name`] is
+/// enabled.
+///
+/// Built when the span is created or its fields are recorded, rather than every time the span is
+/// entered.
+struct ZoneName(String);
+
+/// Marks the spans that end a continuous frame when they are closed.
+struct FrameMark;
+
+/// The number of times a span has been entered without a zone being begun, because the client was
+/// not running yet, and which have not been exited yet.
+struct UnrecordedEntries(usize);
+
+/// The memory pool named after a span, when [`Config::span_memory_pools`] is enabled.
+struct MemoryPool(PoolName);
+
+/// The stack depth of the zones of a span, overriding [`Config::stack_depth`].
+struct CallstackDepth(u16);
+
+/// Looks for the reserved `tracy.frame_mark = true` and `tracy.callstack_depth` span fields.
+#[derive(Default)]
+struct ReservedSpanFields {
+    frame_mark: bool,
+    callstack_depth: Option<u16>,
+}
+
+impl ReservedSpanFields {
+    /// Store the reserved fields found in the extensions of their span.
+    fn store(self, extensions: &mut registry::ExtensionsMut<'_>) {
+        if self.frame_mark && extensions.get_mut::<FrameMark>().is_none() {
+            extensions.insert(FrameMark);
+        }
+        if let Some(depth) = self.callstack_depth {
+            let _ = extensions.replace(CallstackDepth(depth));
+        }
+    }
+}
+
+impl Visit for ReservedSpanFields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if field.name() == "tracy.callstack_depth" {
+            let depth = value.clamp(0, u16::MAX.into());
+            self.callstack_depth = Some(u16::try_from(depth).unwrap_or(u16::MAX));
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "tracy.callstack_depth" {
+            self.callstack_depth = Some(u16::try_from(value).unwrap_or(u16::MAX));
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == "tracy.frame_mark" {
+            self.frame_mark = value;
+        }
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+}
+
+/// Fiber names that are not currently used by any span.
+///
+/// Tracy identifies fibers by the address of their name and never forgets one, so the names are
+/// reused between spans rather than leaked for every span.
+static FIBER_NAMES: Mutex<Vec<FiberName>> = Mutex::new(Vec::new());
+static FIBER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The fiber and the open zones of a span when [`Config::fibers`] is enabled.
+struct FiberState {
+    name: FiberName,
+    zones: Vec<Span>,
+}
+
+// SAFETY: `Span` is not `Send` or `Sync` because a Tracy zone is bound to the thread that began
+// it. With fibers the zone is bound to the fiber instead, and every zone stored here is only ever
+// ended within its fiber. The zones are never accessed through a shared reference.
+unsafe impl Send for FiberState {}
+unsafe impl Sync for FiberState {}
+
+impl FiberState {
+    fn new() -> Self {
+        let name = FIBER_NAMES.lock().ok().and_then(|mut n| n.pop());
+        let name = name.unwrap_or_else(|| {
+            let n = FIBER_COUNT.fetch_add(1, Ordering::Relaxed);
+            FiberName::new_leak(format!("tracing fiber {n}"))
+        });
+        Self {
+            name,
+            zones: Vec::new(),
+        }
+    }
+}
+
+impl Drop for FiberState {
+    fn drop(&mut self) {
+        if !self.zones.is_empty() {
+            let client = Client::start();
+            client.fiber_enter(self.name);
+            self.zones.clear();
+            client.fiber_leave();
+        }
+        if let Ok(mut names) = FIBER_NAMES.lock() {
+            names.push(self.name);
+        }
+    }
 }
 
 /// A tracing layer that collects data in Tracy profiling format.
@@ -87,7 +381,8 @@ thread_local! {
 #[derive(Clone)]
 pub struct TracyLayer<C = DefaultConfig> {
     config: C,
-    client: Client,
+    /// Set once the client is known to be running, see [`TracyLayer::new_manual`].
+    client: OnceLock<Client>,
 }
 
 impl<C> TracyLayer<C> {
@@ -98,20 +393,334 @@ impl<C> TracyLayer<C> {
     pub fn new(config: C) -> Self {
         Self {
             config,
-            client: Client::start(),
+            client: OnceLock::from(Client::start()),
+        }
+    }
+
+    /// Create a new `TracyLayer` without starting the Tracy client.
+    ///
+    /// This is intended for use with the `manual-lifetime` feature, with which the Tracy client
+    /// only starts upon the first [`Client::start`] call. Until then, the layer ignores spans and
+    /// events, rather than starting the client on its own as [`TracyLayer::new`] does. This
+    /// allows for registering the layer unconditionally, while starting the client only under a
+    /// gate of your own (e.g. with [`Client::try_start_if`]).
+    ///
+    /// Without the `manual-lifetime` feature the client starts before `main` regardless, so this
+    /// is the same as [`TracyLayer::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// tracing::subscriber::set_global_default(
+    ///     tracing_subscriber::registry()
+    ///         .with(tracing_tracy::TracyLayer::new_manual(tracing_tracy::DefaultConfig::default()))
+    /// ).expect("setup tracy layer");
+    /// // Spans and events are only reported once the client is started.
+    /// let _client = tracing_tracy::client::Client::try_start_if(std::env::var_os("PROFILE").is_some());
+    /// ```
+    #[must_use]
+    pub fn new_manual(config: C) -> Self {
+        Self {
+            config,
+            client: OnceLock::new(),
+        }
+    }
+}
+
+impl<F> TracyLayer<DefaultConfig<F>> {
+    /// Use `fmt` to format the fields of spans.
+    ///
+    /// This replaces the formatter of the default configuration, leaving the rest of the
+    /// configuration intact.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tracing_subscriber::{fmt::format, layer::SubscriberExt};
+    ///
+    /// // Only include the values of fields.
+    /// let fmt = format::debug_fn(|writer, _, value| write!(writer, "{value:?}"));
+    /// tracing::subscriber::set_global_default(
+    ///     tracing_subscriber::registry()
+    ///         .with(tracing_tracy::TracyLayer::default().with_formatter(fmt))
+    /// ).expect("setup tracy layer");
+    /// ```
+    #[must_use]
+    pub fn with_formatter<F2>(self, fmt: F2) -> TracyLayer<DefaultConfig<F2>>
+    where
+        F2: for<'writer> FormatFields<'writer> + 'static,
+    {
+        TracyLayer {
+            config: DefaultConfig(fmt),
+            client: self.client,
         }
     }
 }
 
 impl<C: Config> TracyLayer<C> {
+    /// The client to report to, or `None` if the client is not running.
+    fn client(&self) -> Option<&Client> {
+        // With `manual-lifetime` the client may be shut down by dropping the last owner returned
+        // by `Client::start_owned`, and started again later, so it is checked on every use.
+        if let Some(client) = self.client.get() {
+            return Client::is_running().then_some(client);
+        }
+        Client::running().map(|client| self.client.get_or_init(|| client))
+    }
+
+    fn fibers(&self) -> bool {
+        cfg!(feature = "fibers") && self.config.fibers()
+    }
+
+    /// Whether another event message fits into the rate limit of the current thread.
+    fn take_message_budget(&self, client: &Client) -> bool {
+        let Some(limit) = self.config.message_rate_limit() else {
+            return true;
+        };
+        let second = EPOCH.get_or_init(Instant::now).elapsed().as_secs();
+        MESSAGE_BUDGET.with(|budget| {
+            let mut current = budget.get();
+            if current.second != second {
+                if current.dropped != 0 {
+                    let summary = format!(
+                        "{} messages dropped due to the message rate limit",
+                        current.dropped
+                    );
+                    client.message(&summary, 0);
+                }
+                current = MessageBudget {
+                    second,
+                    ..MessageBudget::new()
+                };
+            }
+            let allowed = current.sent < limit;
+            if allowed {
+                current.sent += 1;
+            } else {
+                current.dropped += 1;
+            }
+            budget.set(current);
+            allowed
+        })
+    }
+
+    /// Mark the end of a frame, attaching the [`Config::frame_image`] to it.
+    fn frame_mark(&self, client: &Client) {
+        client.frame_mark();
+        let Some((rgba, width, height)) = self.config.frame_image() else {
+            return;
+        };
+        if rgba.len() != usize::from(width) * usize::from(height) * 4 {
+            self.config.on_error(
+                client,
+                "frame image buffer length does not match its dimensions, the image was dropped",
+            );
+        } else if width == 0 || height == 0 || width % 4 != 0 || height % 4 != 0 {
+            self.config.on_error(
+                client,
+                "frame image dimensions are not non-zero multiples of 4, the image was dropped",
+            );
+        } else {
+            // The image belongs to the frame that has just been ended.
+            client.frame_image(&rgba, width, height, 1, false);
+        }
+    }
+
+    /// Report the memory event described by the reserved fields of an event.
+    fn emit_memory(&self, client: &Client, memory: MemoryEvent, metadata: &Metadata<'_>) {
+        let depth = self.config.stack_depth(metadata);
+        match (memory.alloc_ptr, memory.alloc_bytes) {
+            (Some(ptr), Some(bytes)) => match (usize::try_from(ptr), usize::try_from(bytes)) {
+                (Ok(ptr), Ok(bytes)) => client.memory_alloc(memory.pool, ptr, bytes, depth),
+                _ => self.config.on_error(
+                    client,
+                    "memory allocation event values do not fit into `usize`, the event was dropped",
+                ),
+            },
+            (None, None) => {}
+            _ => self.config.on_error(
+                client,
+                "memory allocation events need both `tracy.alloc_ptr` and `tracy.alloc_bytes`",
+            ),
+        }
+        if let Some(ptr) = memory.free_ptr {
+            match usize::try_from(ptr) {
+                Ok(ptr) => client.memory_free(memory.pool, ptr, depth),
+                Err(_) => self.config.on_error(
+                    client,
+                    "memory free event pointer does not fit into `usize`, the event was dropped",
+                ),
+            }
+        }
+    }
+
+    /// Whether the span fields should be formatted, see [`Config::lazy_text`].
+    fn format_fields(&self) -> bool {
+        !self.config.lazy_text() || self.client().is_some_and(Client::is_connected)
+    }
+
+    /// Attribute the allocations on this thread to the pool of the innermost entered span.
+    fn update_memory_pool<S>(&self, client: &Client, ctx: &Context<'_, S>)
+    where
+        S: Subscriber + for<'a> registry::LookupSpan<'a>,
+    {
+        let innermost = TRACY_SPAN_STACK.with(|s| s.rfind_map(|&(_, id)| Some(id)));
+        let pool = innermost
+            .and_then(|id| ctx.span(&Id::from_u64(id)))
+            .map(|span| {
+                let pool = span.extensions().get::<MemoryPool>().map(|pool| pool.0);
+                pool.unwrap_or_else(|| {
+                    let pool = pool_name(span.name());
+                    span.extensions_mut().insert(MemoryPool(pool));
+                    pool
+                })
+            });
+        client.set_thread_memory_pool(pool);
+    }
+
+    /// Whether the span described by `metadata` should be represented as a Tracy zone.
+    fn span_enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.config.min_level() && self.config.span_filter(metadata)
+    }
+
+    /// Whether the span is exiting an entry which was not recorded because the client was not
+    /// running at the time, forgetting about that entry if so.
+    ///
+    /// The entries made while the client runs are nested within the unrecorded ones, so an exit
+    /// only belongs to an unrecorded entry if the span has no zone open anymore.
+    fn exit_unrecorded<S>(&self, span: &registry::SpanRef<'_, S>, running: bool) -> bool
+    where
+        S: for<'a> registry::LookupSpan<'a>,
+    {
+        if span.extensions().get::<UnrecordedEntries>().is_none() {
+            return false;
+        }
+        if running {
+            let zone_open = if self.fibers() {
+                span.extensions()
+                    .get::<FiberState>()
+                    .is_some_and(|state| !state.zones.is_empty())
+            } else {
+                let id = span.id().into_u64();
+                TRACY_SPAN_STACK.with(|s| s.rfind_map(|&(_, i)| (i == id).then_some(())).is_some())
+            };
+            if zone_open {
+                return false;
+            }
+        }
+        let mut extensions = span.extensions_mut();
+        if let Some(UnrecordedEntries(entries)) = extensions.get_mut::<UnrecordedEntries>() {
+            *entries -= 1;
+            if *entries == 0 {
+                extensions.remove::<UnrecordedEntries>();
+            }
+        }
+        true
+    }
+
+    /// Begin a Tracy zone representing `span`.
+    fn span_zone<S>(&self, client: &Client, span: &registry::SpanRef<'_, S>) -> Span
+    where
+        S: for<'a> registry::LookupSpan<'a>,
+    {
+        let extensions = span.extensions();
+        let fields = extensions.get::<TracyFields<C>>();
+        let metadata = span.metadata();
+        let (file, line) = source_file_line(metadata);
+        let depth = match extensions.get::<CallstackDepth>() {
+            Some(depth) => depth.0,
+            None => self.config.stack_depth(metadata),
+        };
+        let colored = |zone: Span| {
+            if let Some(color) = self.config.span_color(metadata) {
+                zone.emit_color(color);
+            }
+            zone
+        };
+        let zone = |name: &str| {
+            colored(client.clone().span_alloc(
+                Some(self.truncate_span_to_length(
+                    client,
+                    name,
+                    file,
+                    metadata.target(),
+                    "span information is too long and was truncated",
+                )),
+                metadata.target(),
+                file,
+                line,
+                depth,
+            ))
+        };
+        // Zones named just after the span can reuse the location interned for its callsite.
+        let named_zone = || match extensions.get::<ZoneLocation>() {
+            Some(location) => colored(client.clone().span(location.0, depth)),
+            None => CACHE.with(|cache| {
+                let mut buf = cache.acquire();
+                write_zone_name(&mut buf, metadata, None, self.config.verbose_names());
+                zone(&buf)
+            }),
+        };
+
+        if let Some(name) = extensions.get::<ZoneName>() {
+            return zone(&name.0);
+        }
+        match fields {
+            None => named_zone(),
+            Some(fields) if fields.is_empty() => named_zone(),
+            Some(fields) if self.config.format_fields_in_zone_name() => CACHE.with(|cache| {
+                let mut buf = cache.acquire();
+                let verbose = self.config.verbose_names();
+                write_zone_name(&mut buf, metadata, Some(&fields.fields), verbose);
+                zone(&buf)
+            }),
+            Some(fields) => {
+                let zone = named_zone();
+                zone.emit_text(self.truncate_to_length(
+                    client,
+                    (u16::MAX - 1).into(),
+                    &fields.fields,
+                    "span field values are too long and were truncated",
+                ));
+                zone
+            }
+        }
+    }
+
+    /// Update the [`ZoneName`] of `span` after its fields have changed.
+    fn cache_zone_name<S>(&self, span: &registry::SpanRef<'_, S>)
+    where
+        S: for<'a> registry::LookupSpan<'a>,
+    {
+        if !self.config.format_fields_in_zone_name() {
+            return;
+        }
+        let mut extensions = span.extensions_mut();
+        let Some(fields) = extensions.get_mut::<TracyFields<C>>() else {
+            return;
+        };
+        if fields.is_empty() {
+            return;
+        }
+        let mut name = CACHE.with(|cache| cache.acquire().into_inner());
+        let verbose = self.config.verbose_names();
+        write_zone_name(&mut name, span.metadata(), Some(&fields.fields), verbose);
+        if let Some(ZoneName(old)) = extensions.replace(ZoneName(name)) {
+            CACHE.with(|cache| drop(StrCacheGuard::new(cache, old)));
+        }
+    }
+
     fn truncate_span_to_length<'a>(
         &self,
+        client: &Client,
         data: &'a str,
         file: &str,
         function: &str,
         error_msg: &'static str,
     ) -> &'a str {
         self.truncate_to_length(
+            client,
             // From AllocSourceLocation
             usize::from(u16::MAX) - 2 - 4 - 4 - function.len() - 1 - file.len() - 1,
             data,
@@ -121,6 +730,7 @@ impl<C: Config> TracyLayer<C> {
 
     fn truncate_to_length<'a>(
         &self,
+        client: &Client,
         mut max_len: usize,
         data: &'a str,
         error_msg: &'static str,
@@ -129,7 +739,7 @@ impl<C: Config> TracyLayer<C> {
             while !data.is_char_boundary(max_len) {
                 max_len -= 1;
             }
-            self.config.on_error(&self.client, error_msg);
+            self.config.on_error(client, error_msg);
             &data[..max_len]
         } else {
             data
@@ -168,11 +778,25 @@ where
     S: Subscriber + for<'a> registry::LookupSpan<'a>,
     C: Config + 'static,
 {
+    fn on_layer(&mut self, _: &mut S) {
+        let Some(client) = self.client() else { return };
+        if self.config.startup_info() {
+            client.app_info(&startup_info());
+        }
+    }
+
     fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
         let Some(span) = ctx.span(id) else { return };
 
         let mut extensions = span.extensions_mut();
-        if extensions.get_mut::<TracyFields<C>>().is_none() {
+        let mut reserved = ReservedSpanFields::default();
+        attrs.record(&mut reserved);
+        reserved.store(&mut extensions);
+        if self.span_enabled(span.metadata()) && extensions.get_mut::<ZoneLocation>().is_none() {
+            let location = zone_location(span.metadata(), self.config.verbose_names());
+            extensions.insert(ZoneLocation(location));
+        }
+        if extensions.get_mut::<TracyFields<C>>().is_none() && self.format_fields() {
             let mut fields =
                 TracyFields::<C>::new(CACHE.with(|cache| cache.acquire().into_inner()));
             if self
@@ -184,12 +808,72 @@ where
                 extensions.insert(fields);
             }
         }
+        drop(extensions);
+        self.cache_zone_name(&span);
     }
 
     fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
         let Some(span) = ctx.span(id) else { return };
 
+        if let (true, Some(client)) = (self.config.record_messages(), self.client()) {
+            CACHE.with(|cache| {
+                let mut buf = cache.acquire();
+                let metadata = span.metadata();
+                let _ = write!(buf, "{}: ", metadata.name());
+                let mut visitor = TracyEventFieldVisitor {
+                    dest: &mut buf,
+                    first: true,
+                    frame_mark: false,
+                    config: &self.config,
+                    plot: None,
+                    memory: None,
+                };
+                values.record(&mut visitor);
+                if !visitor.first {
+                    let message = self.truncate_to_length(
+                        client,
+                        (u16::MAX - 1).into(),
+                        visitor.dest,
+                        "span record message is too long and was truncated",
+                    );
+                    let depth = self.config.stack_depth(metadata);
+                    match self.config.message_color(metadata) {
+                        Some(color) => client.color_message(message, color, depth),
+                        None => client.message(message, depth),
+                    }
+                }
+            });
+        }
+
         let mut extensions = span.extensions_mut();
+        let mut reserved = ReservedSpanFields::default();
+        values.record(&mut reserved);
+        reserved.store(&mut extensions);
+        if !self.format_fields() {
+            return;
+        }
+
+        // Annotate the zone if the span is currently entered on this thread. Records for spans
+        // entered elsewhere (or not at all) only update the stored fields.
+        TRACY_SPAN_STACK.with(|s| {
+            s.rfind_map(|(zone, span_id)| {
+                (*span_id == id.into_u64()).then(|| {
+                    CACHE.with(|cache| {
+                        let mut buf = cache.acquire();
+                        if self
+                            .config
+                            .formatter()
+                            .format_fields(Writer::new(&mut *buf), values)
+                            .is_ok()
+                            && !buf.is_empty()
+                        {
+                            zone.emit_text(&buf);
+                        }
+                    });
+                })
+            })
+        });
+
         if let Some(fields) = extensions.get_mut::<TracyFields<C>>() {
             let _ = self.config.formatter().add_fields(fields, values);
         } else {
@@ -204,104 +888,237 @@ where
                 extensions.insert(fields);
             }
         }
+        drop(extensions);
+        self.cache_zone_name(&span);
     }
 
     fn on_event(&self, event: &Event, _: Context<'_, S>) {
+        let Some(client) = self.client() else { return };
         CACHE.with(|cache| {
             let mut buf = cache.acquire();
+            if self.config.event_location() {
+                write_location(&mut buf, event.metadata());
+            }
             let mut visitor = TracyEventFieldVisitor {
                 dest: &mut buf,
                 first: true,
                 frame_mark: false,
+                config: &self.config,
+                plot: self.config.auto_plot().then_some(client),
+                memory: self.config.memory_events().then(MemoryEvent::default),
             };
 
             event.record(&mut visitor);
-            if !visitor.first {
-                self.client.message(
-                    self.truncate_to_length(
+            if let Some(memory) = visitor.memory.take() {
+                self.emit_memory(client, memory, event.metadata());
+            }
+            if self.config.event_zones() {
+                let metadata = event.metadata();
+                let location = zone_location(metadata, self.config.verbose_names());
+                let zone = client.clone().span(location, 0);
+                if let Some(color) = self.config.message_color(metadata) {
+                    zone.emit_color(color);
+                }
+                // The zone only marks the location of the event, so it ends right away.
+                drop(zone);
+            }
+            if !visitor.first && self.take_message_budget(client) {
+                let metadata = event.metadata();
+                let depth = self.config.stack_depth(metadata);
+                let color = self.config.message_color(metadata);
+                let emit = |message: &str| match color {
+                    Some(color) => client.color_message(message, color, depth),
+                    None => client.message(message, depth),
+                };
+                match self.config.long_message_mode() {
+                    LongMessageMode::Truncate => emit(self.truncate_to_length(
+                        client,
                         (u16::MAX - 1).into(),
                         visitor.dest,
                         "event message is too long and was truncated",
-                    ),
-                    self.config.stack_depth(event.metadata()),
-                );
+                    )),
+                    LongMessageMode::Split => {
+                        split_to_length((u16::MAX - 1).into(), visitor.dest).for_each(emit);
+                    }
+                }
             }
             if visitor.frame_mark {
-                self.client.frame_mark();
+                self.frame_mark(client);
             }
         });
     }
 
     fn on_enter(&self, id: &Id, ctx: Context<S>) {
         let Some(span) = ctx.span(id) else { return };
+        if !self.span_enabled(span.metadata()) {
+            return;
+        }
+        let Some(client) = self.client() else {
+            // Remember the entry, so that its exit is not reported once the client runs.
+            let mut extensions = span.extensions_mut();
+            match extensions.get_mut::<UnrecordedEntries>() {
+                Some(UnrecordedEntries(entries)) => *entries += 1,
+                None => extensions.insert(UnrecordedEntries(1)),
+            }
+            return;
+        };
 
-        let extensions = span.extensions();
-        let fields = extensions.get::<TracyFields<C>>();
-        let stack_frame = {
-            let metadata = span.metadata();
-            let file = metadata.file().unwrap_or("<not available>");
-            let line = metadata.line().unwrap_or(0);
-            let span = |name: &str| {
-                (
-                    self.client.clone().span_alloc(
-                        Some(self.truncate_span_to_length(
-                            name,
-                            file,
-                            "",
-                            "span information is too long and was truncated",
-                        )),
-                        "",
-                        file,
-                        line,
-                        self.config.stack_depth(metadata),
-                    ),
-                    id.into_u64(),
-                )
-            };
+        if self.config.thread_naming() && !THREAD_NAMED.with(|named| named.replace(true)) {
+            if let Some(name) = std::thread::current().name() {
+                client.set_thread_name(name);
+            }
+        }
 
-            match fields {
-                None => span(metadata.name()),
-                Some(fields) if fields.is_empty() => span(metadata.name()),
-                Some(fields) if self.config.format_fields_in_zone_name() => CACHE.with(|cache| {
-                    let mut buf = cache.acquire();
-                    let _ = write!(buf, "{}{{{}}}", metadata.name(), fields.fields);
-                    span(&buf)
-                }),
-                Some(fields) => {
-                    let span = span(metadata.name());
-                    span.0.emit_text(self.truncate_to_length(
-                        (u16::MAX - 1).into(),
-                        &fields.fields,
-                        "span field values are too long and were truncated",
-                    ));
-                    span
+        if self.config.duration_plots() {
+            let mut extensions = span.extensions_mut();
+            if extensions.get_mut::<BusyTime>().is_none() {
+                extensions.insert(BusyTime::default());
+            }
+            if let Some(time) = extensions.get_mut::<BusyTime>() {
+                if time.depth == 0 {
+                    time.entered_at = Some(Instant::now());
                 }
+                time.depth += 1;
             }
+        }
+
+        let fiber = if self.fibers() {
+            let mut extensions = span.extensions_mut();
+            if extensions.get_mut::<FiberState>().is_none() {
+                extensions.insert(FiberState::new());
+            }
+            extensions.get_mut::<FiberState>().map(|state| state.name)
+        } else {
+            None
         };
+        if let Some(name) = fiber {
+            client.fiber_enter(name);
+        }
+
+        let zone = self.span_zone(client, &span);
+        if self.config.thread_name_in_zone() {
+            CACHE.with(|cache| {
+                let mut buf = cache.acquire();
+                write_thread_name(&mut buf, &std::thread::current());
+                zone.emit_text(&buf);
+            });
+        }
+        if self.config.parent_annotation() {
+            CACHE.with(|cache| {
+                let mut buf = cache.acquire();
+                if write_parent_annotation(&mut buf, &span) {
+                    zone.emit_text(&buf);
+                }
+            });
+        }
+
+        if fiber.is_some() {
+            if let Some(state) = span.extensions_mut().get_mut::<FiberState>() {
+                state.zones.push(zone);
+            }
+            client.fiber_leave();
+            return;
+        }
 
         TRACY_SPAN_STACK.with(|s| {
-            s.push(stack_frame);
+            s.push((zone, id.into_u64()));
         });
+        if self.config.span_memory_pools() {
+            self.update_memory_pool(client, &ctx);
+        }
     }
 
-    fn on_exit(&self, id: &Id, _: Context<S>) {
-        let stack_frame = TRACY_SPAN_STACK.with(VecCell::pop);
+    fn on_exit(&self, id: &Id, ctx: Context<S>) {
+        if let Some(metadata) = ctx.metadata(id) {
+            if !self.span_enabled(metadata) {
+                return;
+            }
+        }
+        let Some(client) = self.client() else {
+            if let Some(span) = ctx.span(id) {
+                self.exit_unrecorded(&span, false);
+            }
+            return;
+        };
+        if let Some(span) = ctx.span(id) {
+            if self.exit_unrecorded(&span, true) {
+                return;
+            }
+        }
+
+        if self.config.duration_plots() {
+            if let Some(span) = ctx.span(id) {
+                if let Some(time) = span.extensions_mut().get_mut::<BusyTime>() {
+                    time.depth = time.depth.saturating_sub(1);
+                    if let Some(entered_at) = time.entered_at.filter(|_| time.depth == 0) {
+                        time.busy = Some(time.busy.unwrap_or_default() + entered_at.elapsed());
+                        time.entered_at = None;
+                    }
+                }
+            }
+        }
 
-        if let Some((span, span_id)) = stack_frame {
-            if id.into_u64() != span_id {
+        if self.fibers() {
+            let Some(span) = ctx.span(id) else { return };
+            let mut extensions = span.extensions_mut();
+            let Some(state) = extensions.get_mut::<FiberState>() else {
+                return;
+            };
+            if let Some(zone) = state.zones.pop() {
+                client.fiber_enter(state.name);
+                drop(zone);
+                client.fiber_leave();
+            } else {
                 self.config.on_error(
-                    &self.client,
-                    "Tracing spans exited out of order! \
-                        Trace might not be accurate for this span stack.",
+                    client,
+                    "Exiting a tracing span, but it has no open tracy zone!",
                 );
             }
+            return;
+        }
+
+        let id = id.into_u64();
+        let on_stack = TRACY_SPAN_STACK.with(|s| s.rfind_map(|&(_, i)| (i == id).then_some(())));
+        if on_stack.is_none() {
+            // The span was entered on another thread, or its entry was not seen at all (e.g. when
+            // the registry could not find it). The zones on this thread belong to other spans.
+            self.config.on_error(
+                client,
+                "Exiting a tracing span which has no tracy zone open on this thread! \
+                    The tracy span stack was left untouched.",
+            );
+            return;
+        }
+
+        // Tracy requires zones to be ended in the reverse order they were begun. If the exiting
+        // span is not at the top of the stack, end the zones above it as well and begin new ones
+        // for those spans once the exiting span's zone has been ended.
+        let mut reenter = Vec::new();
+        while let Some((span, span_id)) = TRACY_SPAN_STACK.with(VecCell::pop) {
             drop(span);
-        } else {
+            if span_id == id {
+                break;
+            }
+            reenter.push(span_id);
+        }
+        if !reenter.is_empty() {
             self.config.on_error(
-                &self.client,
-                "Exiting a tracing span, but got nothing on the tracy span stack!",
+                client,
+                "Tracing spans exited out of order! \
+                    Trace might not be accurate for this span stack.",
             );
         }
+        for span_id in reenter.into_iter().rev() {
+            let zone = ctx
+                .span(&Id::from_u64(span_id))
+                .map(|span| self.span_zone(client, &span));
+            if let Some(zone) = zone {
+                TRACY_SPAN_STACK.with(|s| s.push((zone, span_id)));
+            }
+        }
+        if self.config.span_memory_pools() {
+            self.update_memory_pool(client, &ctx);
+        }
     }
 
     fn on_close(&self, id: Id, ctx: Context<'_, S>) {
@@ -311,16 +1128,131 @@ where
             let buf = mem::take(&mut fields.fields);
             CACHE.with(|cache| drop(StrCacheGuard::new(cache, buf)));
         };
+        if let Some(ZoneName(name)) = span.extensions_mut().remove::<ZoneName>() {
+            CACHE.with(|cache| drop(StrCacheGuard::new(cache, name)));
+        }
+
+        let Some(client) = self.client() else { return };
+        let busy = span
+            .extensions()
+            .get::<BusyTime>()
+            .and_then(|time| time.busy);
+        if let Some(busy) = busy {
+            let name = plot_name(span.metadata().name());
+            client.plot(name, busy.as_secs_f64());
+        }
+
+        if span.extensions().get::<FrameMark>().is_some() {
+            self.frame_mark(client);
+        }
+    }
+}
+
+/// The application information reported when [`Config::startup_info`] is enabled.
+fn startup_info() -> String {
+    format!("tracy-client features: {}", client::feature_summary())
+}
+
+/// Write the name of `thread`, or its id if it is unnamed, see [`Config::thread_name_in_zone`].
+fn write_thread_name(dest: &mut String, thread: &std::thread::Thread) {
+    match thread.name() {
+        Some(name) => dest.push_str(name),
+        None => {
+            let _ = write!(dest, "{:?}", thread.id());
+        }
+    }
+}
+
+/// Write the annotation of the zones of `span` naming its parent, see
+/// [`Config::parent_annotation`]. Returns whether the span has a parent to annotate it with.
+fn write_parent_annotation<S>(dest: &mut String, span: &registry::SpanRef<'_, S>) -> bool
+where
+    S: for<'a> registry::LookupSpan<'a>,
+{
+    let Some(parent) = span.parent() else {
+        return false;
+    };
+    let _ = write!(dest, "parent = {}", parent.name());
+    true
+}
+
+/// Write the `file:line: ` prefix for a message about the event described by `metadata`.
+fn write_location(dest: &mut String, metadata: &Metadata<'_>) {
+    match (metadata.file(), metadata.line()) {
+        (Some(file), Some(line)) => {
+            let _ = write!(dest, "{file}:{line}: ");
+        }
+        (Some(file), None) => {
+            let _ = write!(dest, "{file}: ");
+        }
+        (None, _) => {}
     }
 }
 
-struct TracyEventFieldVisitor<'a> {
+/// Split `data` along character boundaries into pieces shorter than `max_len` bytes.
+fn split_to_length(max_len: usize, mut data: &str) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        if data.is_empty() {
+            return None;
+        }
+        let mut len = data.len().min(max_len - 1);
+        while !data.is_char_boundary(len) {
+            len -= 1;
+        }
+        let (piece, rest) = data.split_at(len);
+        data = rest;
+        Some(piece)
+    })
+}
+
+struct TracyEventFieldVisitor<'a, C> {
     dest: &'a mut String,
     frame_mark: bool,
     first: bool,
+    config: &'a C,
+    /// The client to plot the numeric fields with, if they should be plotted.
+    plot: Option<&'a Client>,
+    /// The reserved memory fields, if they should be recognized.
+    memory: Option<MemoryEvent>,
 }
 
-impl Visit for TracyEventFieldVisitor<'_> {
+impl<C: Config> TracyEventFieldVisitor<'_, C> {
+    fn plot(&self, field: &Field, value: f64) {
+        if let Some(client) = self.plot {
+            client.plot(plot_name(field.name()), value);
+        }
+    }
+
+    /// Store `value` if `field` is one of the reserved memory fields and they are recognized.
+    fn record_memory(&mut self, field: &Field, value: u64) -> bool {
+        self.memory
+            .as_mut()
+            .is_some_and(|memory| memory.record(field, value))
+    }
+}
+
+impl<C: Config> Visit for TracyEventFieldVisitor<'_, C> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if u64::try_from(value).is_ok_and(|value| self.record_memory(field, value)) {
+            return;
+        }
+        self.plot(field, value as f64);
+        self.record_debug(field, &value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if self.record_memory(field, value) {
+            return;
+        }
+        self.plot(field, value as f64);
+        self.record_debug(field, &value);
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.plot(field, value);
+        self.record_debug(field, &value);
+    }
+
     fn record_bool(&mut self, field: &Field, value: bool) {
         match (value, field.name()) {
             (_, "tracy.frame_mark") => self.frame_mark = value,
@@ -330,29 +1262,26 @@ impl Visit for TracyEventFieldVisitor<'_> {
     }
 
     fn record_str(&mut self, field: &Field, value: &str) {
-        let name = field.name();
-        let alloc_always_size = name.len() + " = ".len() + value.len();
-        if self.first {
-            self.dest.reserve(alloc_always_size);
-            self.first = false;
-        } else {
-            self.dest.reserve(", ".len() + alloc_always_size);
-            self.dest.push_str(", ");
+        if let (Some(memory), "tracy.alloc_pool") = (&mut self.memory, field.name()) {
+            memory.pool = Some(pool_name(value));
+            return;
         }
-
-        self.dest.push_str(name);
-        self.dest.push_str(" = ");
-        self.dest.push_str(value);
+        // `Arguments` are debug-formatted without quotes, unlike `str`.
+        self.record_debug(field, &format_args!("{value}"));
     }
 
     fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
-        // FIXME: this is a very crude formatter, but we don’t have
-        // an easy way to do anything better...
-        if self.first {
-            self.first = false;
-            let _ = write!(self.dest, "{} = {value:?}", field.name());
+        let start = self.dest.len();
+        if !self.first {
+            self.dest.push_str(", ");
+        }
+        let separated = self.dest.len();
+        self.config.format_event_field(self.dest, field, value);
+        if self.dest.len() == separated {
+            // The field was omitted by the formatter.
+            self.dest.truncate(start);
         } else {
-            let _ = write!(self.dest, ", {} = {value:?}", field.name());
+            self.first = false;
         }
     }
 }
@@ -396,6 +1325,16 @@ mod utils {
             // In addition, this method is not re-entrant.
             unsafe { &mut *self.0.get() }.pop()
         }
+
+        /// Apply `f` to the items from last to first, returning the first `Some` it produces.
+        ///
+        /// `f` must not access this `VecCell`.
+        pub fn rfind_map<R>(&self, f: impl FnMut(&T) -> Option<R>) -> Option<R> {
+            // SAFETY:
+            // The reference to the contents of the UnsafeCell remain strictly within this method
+            // and `f` does not access the cell. In addition, this method is not re-entrant.
+            unsafe { &*self.0.get() }.iter().rev().find_map(f)
+        }
     }
 
     pub struct StrCache {
@@ -411,7 +1350,7 @@ mod utils {
             }
         }
 
-        pub fn acquire(&self) -> StrCacheGuard {
+        pub fn acquire(&self) -> StrCacheGuard<'_> {
             StrCacheGuard::new(
                 self,
                 self.str_bufs
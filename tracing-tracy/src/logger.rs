@@ -0,0 +1,85 @@
+use client::Client;
+use std::fmt::Write;
+
+/// A [`log::Log`] implementation forwarding the log records to Tracy as messages.
+///
+/// This makes the diagnostics of libraries using the `log` crate, rather than `tracing`, visible
+/// in the profiler. Messages are coloured with [`level_color`](crate::level_color), same as the
+/// tracing events are with a [`Config`](crate::Config) that opts into it.
+///
+/// # Examples
+///
+/// ```
+/// let logger = tracing_tracy::TracyLogger::new(log::LevelFilter::Info);
+/// log::set_max_level(logger.max_level());
+/// log::set_boxed_logger(Box::new(logger)).expect("setup tracy logger");
+/// ```
+pub struct TracyLogger {
+    client: Client,
+    max_level: log::LevelFilter,
+}
+
+impl TracyLogger {
+    /// Create a logger forwarding the records at `max_level` or more severe.
+    ///
+    /// Note that the `log` crate discards the records above [`log::max_level`] before they reach
+    /// any logger, so it should be set to [`TracyLogger::max_level`] or higher as well.
+    #[must_use]
+    pub fn new(max_level: log::LevelFilter) -> Self {
+        Self {
+            client: Client::start(),
+            max_level,
+        }
+    }
+
+    /// The least severe level of the records forwarded by this logger.
+    #[must_use]
+    pub fn max_level(&self) -> log::LevelFilter {
+        self.max_level
+    }
+}
+
+impl log::Log for TracyLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= self.max_level
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        crate::CACHE.with(|cache| {
+            let mut message = cache.acquire();
+            let _ = write!(message, "{}", record.args());
+            let max_len = usize::from(u16::MAX) - 1;
+            let truncated = message.len() >= max_len;
+            let message = crate::split_to_length(max_len, &message)
+                .next()
+                .unwrap_or_default();
+            match level_color(record.level()) {
+                Some(color) => self.client.color_message(message, color, 0),
+                None => self.client.message(message, 0),
+            }
+            if truncated {
+                self.client.color_message(
+                    "log record is too long and was truncated",
+                    client::Color::RED,
+                    0,
+                );
+            }
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// The color of the messages for log records at `level`.
+pub(crate) fn level_color(level: log::Level) -> Option<u32> {
+    crate::level_color(&match level {
+        log::Level::Error => tracing_core::Level::ERROR,
+        log::Level::Warn => tracing_core::Level::WARN,
+        log::Level::Info => tracing_core::Level::INFO,
+        log::Level::Debug => tracing_core::Level::DEBUG,
+        log::Level::Trace => tracing_core::Level::TRACE,
+    })
+}
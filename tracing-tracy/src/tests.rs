@@ -1,12 +1,32 @@
-use crate::{Config, DefaultConfig};
+use crate::{level_color, Config, DefaultConfig};
 
-use super::TracyLayer;
+use super::{TracyLayer, TRACY_SPAN_STACK};
 use criterion::Criterion;
 use futures::future::join_all;
 use tracing::{debug, event, info, info_span, span, Level};
 use tracing_attributes::instrument;
 use tracing_subscriber::layer::SubscriberExt;
 
+/// The counters shared between the test configs and the tests.
+type Counter = std::sync::Arc<std::sync::atomic::AtomicUsize>;
+
+/// Declare a `Config` delegating to [`DefaultConfig`] in its first field, except for the given
+/// methods. The types of any further fields, such as counters, may be listed after the name.
+macro_rules! test_config {
+    ($name: ident $(($($field: ty),+))? { $($methods: tt)* }) => {
+        #[derive(Default)]
+        struct $name(DefaultConfig $($(, $field)+)?);
+
+        impl Config for $name {
+            type Formatter = <DefaultConfig as Config>::Formatter;
+            fn formatter(&self) -> &Self::Formatter {
+                self.0.formatter()
+            }
+            $($methods)*
+        }
+    };
+}
+
 fn it_works() {
     let span = span!(Level::TRACE, "a sec");
     let _enter = span.enter();
@@ -32,6 +52,17 @@ fn multiple_entries() {
     span.in_scope(|| span.in_scope(|| {}));
 }
 
+fn zone_per_entry() {
+    let span = span!(Level::INFO, "zone per entry");
+    let id = span.id().map(|id| id.into_u64());
+    let base = stack_ids();
+    for _ in 0..2 {
+        // Each entry begins a zone of its own, which is ended upon the exit.
+        span.in_scope(|| assert_eq!(stack_ids().last().copied(), id));
+        assert_eq!(stack_ids(), base);
+    }
+}
+
 fn out_of_order() {
     let span1 = span!(Level::INFO, "out of order exits 1");
     let span2 = span!(Level::INFO, "out of order exits 2");
@@ -44,6 +75,175 @@ fn out_of_order() {
     drop(entry1);
 }
 
+fn stack_ids() -> Vec<u64> {
+    let mut ids = Vec::new();
+    TRACY_SPAN_STACK.with(|s| {
+        s.rfind_map(|&(_, id)| {
+            ids.insert(0, id);
+            None::<()>
+        })
+    });
+    ids
+}
+
+fn out_of_order_reentered() {
+    let a = span!(Level::INFO, "out of order A");
+    let b = span!(Level::INFO, "out of order B");
+    let base = stack_ids();
+    let entry_a = a.enter();
+    let entry_b = b.enter();
+    drop(entry_a);
+    // B's zone was ended together with A's and then begun again.
+    let ids = stack_ids();
+    assert_eq!(&ids[..base.len()], base);
+    assert_eq!(&ids[base.len()..], [b.id().unwrap().into_u64()]);
+    drop(entry_b);
+    assert_eq!(stack_ids(), base);
+}
+
+test_config!(ErrorCountingConfig(Counter) {
+    fn on_error(&self, client: &client::Client, error: &'static str) {
+        self.1.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.0.on_error(client, error);
+    }
+});
+
+fn exit_without_enter() {
+    let config = ErrorCountingConfig::default();
+    let errors = std::sync::Arc::clone(&config.1);
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(config));
+    tracing::subscriber::with_default(subscriber, || {
+        let entered = span!(Level::INFO, "entered");
+        let never_entered = span!(Level::INFO, "never entered");
+        let base = stack_ids();
+        let entry = entered.enter();
+        let stack = stack_ids();
+        tracing::dispatcher::get_default(|dispatch| dispatch.exit(&never_entered.id().unwrap()));
+        assert_eq!(stack_ids(), stack, "unrelated zones must stay on the stack");
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 1);
+        drop(entry);
+        assert_eq!(stack_ids(), base);
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 1);
+    });
+}
+
+test_config!(ZoneNameCountingConfig(Counter) {
+    fn format_fields_in_zone_name(&self) -> bool {
+        self.1.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        true
+    }
+});
+
+fn zone_name_cached() {
+    let config = ZoneNameCountingConfig::default();
+    let builds = std::sync::Arc::clone(&config.1);
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(config));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = span!(
+            Level::INFO,
+            "polled",
+            task = 42,
+            state = tracing::field::Empty
+        );
+        assert_eq!(builds.load(std::sync::atomic::Ordering::Relaxed), 1);
+        for _ in 0..100 {
+            drop(span.enter());
+        }
+        assert_eq!(builds.load(std::sync::atomic::Ordering::Relaxed), 1);
+        span.record("state", "ready");
+        for _ in 0..100 {
+            drop(span.enter());
+        }
+        assert_eq!(builds.load(std::sync::atomic::Ordering::Relaxed), 2);
+    });
+}
+
+test_config!(DiagnosticColorConfig(Counter) {
+    fn diagnostic_color(&self) -> u32 {
+        self.1.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        0xFFA500FF
+    }
+});
+
+fn diagnostic_color() {
+    let default = DefaultConfig::default().diagnostic_color();
+    assert_eq!(Some(default), level_color(&Level::ERROR));
+    assert_ne!(default >> 8, 0, "diagnostics must not be rendered black");
+    let config = DiagnosticColorConfig::default();
+    let uses = std::sync::Arc::clone(&config.1);
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(config));
+    tracing::subscriber::with_default(subscriber, || {
+        let span1 = span!(Level::INFO, "diagnostic color 1");
+        let span2 = span!(Level::INFO, "diagnostic color 2");
+        let entry1 = span1.enter();
+        let entry2 = span2.enter();
+        drop(entry1);
+        drop(entry2);
+    });
+    assert_eq!(uses.load(std::sync::atomic::Ordering::Relaxed), 1);
+}
+
+fn reentered_span() {
+    let config = ErrorCountingConfig::default();
+    let errors = std::sync::Arc::clone(&config.1);
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(config));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = span!(Level::INFO, "recursive");
+        let id = span.id().unwrap().into_u64();
+        let base = stack_ids();
+        let outer = span.enter();
+        let inner = span.enter();
+        assert_eq!(stack_ids()[base.len()..], [id, id]);
+        drop(inner);
+        assert_eq!(stack_ids()[base.len()..], [id]);
+        drop(outer);
+        assert_eq!(stack_ids(), base);
+
+        fn walk(span: &tracing::Span, depth: usize) {
+            let _entry = span.enter();
+            if depth > 0 {
+                walk(span, depth - 1);
+            }
+        }
+        walk(&span, 8);
+        assert_eq!(stack_ids(), base);
+    });
+    let errors = errors.load(std::sync::atomic::Ordering::Relaxed);
+    assert_eq!(errors, 0, "re-entering a span is not an error");
+}
+
+test_config!(RecordMessagesConfig {
+    fn record_messages(&self) -> bool {
+        true
+    }
+});
+
+fn record_messages() {
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(RecordMessagesConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        let base = stack_ids();
+        let connection = info_span!(
+            "connection",
+            state = tracing::field::Empty,
+            bytes = tracing::field::Empty
+        );
+        // Never entered, but the records are still valid.
+        connection.record("state", "handshake");
+        assert_eq!(stack_ids(), base);
+        connection.in_scope(|| {
+            connection.record("state", "established");
+            connection.record("bytes", 1024);
+            assert_eq!(
+                stack_ids()[base.len()..],
+                [connection.id().unwrap().into_u64()]
+            );
+        });
+        connection.record("state", "closed");
+        assert_eq!(stack_ids(), base);
+    });
+}
+
 fn exit_in_different_thread() {
     let span = Box::new(span!(Level::INFO, "exit in different thread"));
     let entry = span.enter();
@@ -89,11 +289,91 @@ fn message_too_long() {
     info!("{}", "a".repeat(u16::MAX.into()));
 }
 
+test_config!(SplitConfig {
+    fn long_message_mode(&self) -> crate::LongMessageMode {
+        crate::LongMessageMode::Split
+    }
+});
+
+test_config!(RateLimitConfig {
+    fn message_rate_limit(&self) -> Option<u32> {
+        Some(5)
+    }
+});
+
+fn message_rate_limit() {
+    let budget = || super::MESSAGE_BUDGET.with(std::cell::Cell::get);
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(RateLimitConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        let mut dropped = 0;
+        for i in 0..100 {
+            let before = budget();
+            info!("rate limited message {i}");
+            let after = budget();
+            assert!(after.sent <= 5);
+            if after.dropped > 0 && (after.second, after.dropped) != (before.second, before.dropped)
+            {
+                dropped += 1;
+            }
+        }
+        // The messages may straddle at most a couple of seconds.
+        assert!(dropped >= 90, "only {dropped} messages were dropped");
+    });
+}
+
+fn message_split() {
+    let message = "a€".repeat(100);
+    let pieces = super::split_to_length(8, &message).collect::<Vec<_>>();
+    assert!(pieces.iter().all(|p| !p.is_empty() && p.len() < 8));
+    assert_eq!(pieces[..2], ["a€a", "€a€"]);
+    assert_eq!(pieces.concat(), message);
+    assert_eq!(super::split_to_length(8, "").count(), 0);
+
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(SplitConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        info!("{}", "€".repeat(usize::from(u16::MAX) / 2));
+    });
+}
+
 fn long_span_data() {
     let data = "c".repeat(u16::MAX.into());
     info_span!("some span name", "{}", data).in_scope(|| {});
 }
 
+fn empty_span_fields() {
+    use tracing_subscriber::registry::{LookupSpan, Registry};
+    let fields = |span: &tracing::Span| {
+        let id = span.id().unwrap();
+        tracing::dispatcher::get_default(|dispatch| {
+            let registry = dispatch.downcast_ref::<Registry>().unwrap();
+            let span = registry.span(&id).unwrap();
+            let extensions = span.extensions();
+            let fields = extensions.get::<super::TracyFields<DefaultConfig>>();
+            fields.map(|fields| fields.fields.clone())
+        })
+    };
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::default());
+    tracing::subscriber::with_default(subscriber, || {
+        // The zone of a span without any field values is named just after the span.
+        let fieldless = info_span!("fields", value = tracing::field::Empty);
+        assert_eq!(fields(&fieldless).as_deref(), Some(""));
+        fieldless.in_scope(|| {});
+        let fielded = info_span!("fields", value = 1);
+        assert_eq!(fields(&fielded).as_deref(), Some("value=1"));
+        fielded.in_scope(|| {});
+    });
+}
+
+fn span_target() {
+    span!(target: "app::parser", Level::INFO, "targeted").in_scope(|| {});
+    let target_data = "t".repeat(usize::from(u16::MAX) / 2);
+    span!(target: "app::parser", Level::INFO, "targeted with fields", data = %target_data)
+        .in_scope(|| {});
+    let data = "c".repeat(u16::MAX.into());
+    span!(target: "app::parser", Level::INFO, "long targeted", "{}", data).in_scope(|| {});
+}
+
 fn span_with_fields() {
     let span = span!(
         Level::TRACE,
@@ -104,7 +384,879 @@ fn span_with_fields() {
     let _enter = span.enter();
 }
 
+test_config!(LevelColorConfig {
+    fn span_color(&self, metadata: &tracing_core::Metadata<'_>) -> Option<u32> {
+        level_color(metadata.level())
+    }
+    fn message_color(&self, metadata: &tracing_core::Metadata<'_>) -> Option<u32> {
+        level_color(metadata.level())
+    }
+});
+
+fn level_colors() {
+    assert_eq!(level_color(&Level::ERROR), Some(0xFF0000FF));
+    assert_eq!(level_color(&Level::WARN), Some(0xFFA500FF));
+    assert_eq!(level_color(&Level::INFO), None);
+    assert_eq!(level_color(&Level::TRACE), None);
+
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(LevelColorConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        span!(Level::ERROR, "red span").in_scope(|| {
+            span!(Level::WARN, "orange span").in_scope(|| {
+                span!(Level::INFO, "uncolored span").in_scope(|| {});
+            });
+        });
+        tracing::error!("red message");
+        tracing::warn!("orange message");
+        tracing::info!("uncolored message");
+        message_too_long();
+    });
+}
+
+fn record_fields() {
+    let span = span!(Level::INFO, "recorded", progress = tracing::field::Empty);
+    span.in_scope(|| {
+        span.record("progress", 42);
+        let inner = span!(Level::INFO, "inner");
+        inner.in_scope(|| span.record("progress", 43));
+    });
+    // Not entered at all, and entered on another thread. Neither may disturb the stack.
+    let other = span!(Level::INFO, "other", progress = tracing::field::Empty);
+    other.record("progress", 1);
+    span.in_scope(|| {
+        std::thread::scope(|scope| {
+            scope.spawn(|| span.record("progress", 44));
+        });
+        other.in_scope(|| {});
+    });
+}
+
+test_config!(FiberConfig {
+    fn fibers(&self) -> bool {
+        true
+    }
+});
+
+fn fibers() {
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(FiberConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        multiple_entries();
+        out_of_order();
+        exit_in_different_thread();
+        let span = span!(Level::INFO, "entered here, exited there");
+        let entry = span.enter();
+        let inner = span!(Level::INFO, "nested in fiber");
+        inner.in_scope(|| {});
+        std::thread::scope(|scope| {
+            scope.spawn(move || drop(entry));
+        });
+    });
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(FiberConfig::default()));
+    let _guard = tracing::subscriber::set_default(subscriber);
+    runtime.block_on(async_futures());
+}
+
+test_config!(TargetFilterConfig {
+    fn span_filter(&self, metadata: &tracing_core::Metadata<'_>) -> bool {
+        metadata.target() != "noisy"
+    }
+});
+
+fn span_filter() {
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(TargetFilterConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        let base = stack_ids();
+        let kept = span!(Level::INFO, "kept");
+        let noisy = span!(target: "noisy", Level::INFO, "filtered");
+        kept.in_scope(|| {
+            noisy.in_scope(|| {
+                assert_eq!(stack_ids()[base.len()..], [kept.id().unwrap().into_u64()]);
+            });
+            assert_eq!(stack_ids()[base.len()..], [kept.id().unwrap().into_u64()]);
+        });
+        let entry = noisy.enter();
+        kept.in_scope(|| {});
+        drop(entry);
+        assert_eq!(stack_ids(), base);
+    });
+}
+
+test_config!(MinLevelConfig {
+    fn min_level(&self) -> tracing_core::LevelFilter {
+        tracing_core::LevelFilter::INFO
+    }
+});
+
+fn min_level() {
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(MinLevelConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        let base = stack_ids();
+        let info = span!(Level::INFO, "info");
+        let trace = span!(Level::TRACE, "trace");
+        info.in_scope(|| {
+            trace.in_scope(|| {
+                assert_eq!(stack_ids()[base.len()..], [info.id().unwrap().into_u64()]);
+            });
+            assert_eq!(stack_ids()[base.len()..], [info.id().unwrap().into_u64()]);
+        });
+        assert_eq!(stack_ids(), base);
+        span!(Level::ERROR, "error").in_scope(|| {
+            assert_eq!(stack_ids().len(), base.len() + 1);
+        });
+    });
+}
+
+fn default_config_inference() {
+    let _ = TracyLayer::new(DefaultConfig::default());
+}
+
+fn custom_formatter() {
+    let fmt = tracing_subscriber::fmt::format::debug_fn(|writer, field, value| {
+        if field.name() == "noisy" {
+            Ok(())
+        } else {
+            write!(writer, "{value:?}")
+        }
+    });
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::default().with_formatter(fmt));
+    tracing::subscriber::with_default(subscriber, || {
+        span!(Level::INFO, "formatted", noisy = true, value = 42).in_scope(|| {});
+    });
+}
+
+fn interned_locations() {
+    let interned = || {
+        super::LOCATIONS
+            .get()
+            .map_or(0, |l| l.read().unwrap().len())
+    };
+    tracing::subscriber::with_default(
+        tracing_subscriber::registry().with(TracyLayer::default()),
+        || {
+            let before = interned();
+            for i in 0..100 {
+                span!(Level::INFO, "interned").in_scope(|| {});
+                span!(Level::INFO, "interned with fields", i).in_scope(|| {});
+            }
+            let span = span!(Level::INFO, "entered repeatedly");
+            for _ in 0..100 {
+                span.in_scope(|| {});
+            }
+            assert_eq!(interned(), before + 3);
+        },
+    );
+}
+
+test_config!(AutoPlotConfig {
+    fn auto_plot(&self) -> bool {
+        true
+    }
+});
+
+fn auto_plot() {
+    let plotted = |name| {
+        super::PLOTS
+            .get()
+            .is_some_and(|p| p.read().unwrap().contains_key(name))
+    };
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(AutoPlotConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        for i in 0..100_u32 {
+            info!(
+                queue_depth = i,
+                load = f64::from(i) / 100.0,
+                delta = -i64::from(i)
+            );
+            info!(name = "not a number", flag = true, "message");
+        }
+    });
+    assert!(plotted("queue_depth") && plotted("load") && plotted("delta"));
+    assert!(!plotted("name") && !plotted("flag") && !plotted("message"));
+    info!(not_plotted = 42);
+    assert!(!plotted("not_plotted"));
+}
+
+test_config!(ThreadNamingConfig {
+    fn thread_naming(&self) -> bool {
+        true
+    }
+});
+
+fn thread_naming() {
+    let enter_spans = || {
+        let subscriber =
+            tracing_subscriber::registry().with(TracyLayer::new(ThreadNamingConfig::default()));
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!super::THREAD_NAMED.with(std::cell::Cell::get));
+            info_span!("first").in_scope(|| {});
+            assert!(super::THREAD_NAMED.with(std::cell::Cell::get));
+            info_span!("second").in_scope(|| {});
+        });
+    };
+    std::thread::Builder::new()
+        .name("named worker".into())
+        .spawn(enter_spans)
+        .unwrap()
+        .join()
+        .unwrap();
+    std::thread::spawn(enter_spans).join().unwrap();
+}
+
+test_config!(DurationPlotConfig {
+    fn duration_plots(&self) -> bool {
+        true
+    }
+});
+
+test_config!(VerboseNamesConfig {
+    fn verbose_names(&self) -> bool {
+        true
+    }
+});
+
+fn verbose_names() {
+    let (span, line) = (info_span!("verbose", value = 1), line!());
+    let metadata = span.metadata().unwrap();
+    let name = |fields, verbose| {
+        let mut name = String::new();
+        super::write_zone_name(&mut name, metadata, fields, verbose);
+        name
+    };
+    assert_eq!(name(None, false), "verbose");
+    assert_eq!(name(Some("value=1"), false), "verbose{value=1}");
+    let location = format!("{}:{line}", file!());
+    assert_eq!(
+        name(None, true),
+        format!("tracing_tracy::tests::verbose @ {location}")
+    );
+    assert_eq!(
+        name(Some("value=1"), true),
+        format!("tracing_tracy::tests::verbose{{value=1}} @ {location}")
+    );
+
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(VerboseNamesConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        info_span!("verbose without fields").in_scope(|| {});
+        info_span!("verbose with fields", value = 1).in_scope(|| {});
+    });
+}
+
+test_config!(ParentAnnotationConfig {
+    fn parent_annotation(&self) -> bool {
+        true
+    }
+});
+
+fn parent_annotation() {
+    use tracing_subscriber::registry::{LookupSpan, Registry};
+    let annotation = |span: &tracing::Span| {
+        let id = span.id().unwrap();
+        tracing::dispatcher::get_default(|dispatch| {
+            let registry = dispatch.downcast_ref::<Registry>().unwrap();
+            let mut annotation = String::new();
+            let annotated =
+                super::write_parent_annotation(&mut annotation, &registry.span(&id).unwrap());
+            annotated.then_some(annotation)
+        })
+    };
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(ParentAnnotationConfig::default()));
+    let dispatch = tracing::Dispatch::new(subscriber);
+    tracing::dispatcher::with_default(&dispatch, || {
+        let root = info_span!("annotated root");
+        let _root = root.enter();
+        let lexical = info_span!("lexical child");
+        lexical.in_scope(|| {});
+        let detached = info_span!(parent: None, "detached root");
+        let explicit = info_span!(parent: &root, "explicit child");
+        assert_eq!(annotation(&root), None);
+        assert_eq!(
+            annotation(&lexical).as_deref(),
+            Some("parent = annotated root")
+        );
+        assert_eq!(annotation(&detached), None);
+        assert_eq!(
+            annotation(&explicit).as_deref(),
+            Some("parent = annotated root")
+        );
+        let thread = std::thread::spawn({
+            let dispatch = dispatch.clone();
+            move || {
+                // Closing a span with a parent refers to the default dispatcher, so these must be
+                // dropped while the dispatcher which created them is still the default.
+                tracing::dispatcher::with_default(&dispatch, move || {
+                    explicit.in_scope(|| {});
+                    detached.in_scope(|| {});
+                });
+            }
+        });
+        thread.join().unwrap();
+    });
+}
+
+test_config!(ThreadNameInZoneConfig {
+    fn thread_name_in_zone(&self) -> bool {
+        true
+    }
+});
+
+fn thread_name_in_zone() {
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(ThreadNameInZoneConfig::default()));
+    let dispatch = tracing::Dispatch::new(subscriber);
+    let threads = [Some("named zone thread"), None].map(|name| {
+        let dispatch = dispatch.clone();
+        let builder = std::thread::Builder::new();
+        let builder = match name {
+            Some(name) => builder.name(name.to_owned()),
+            None => builder,
+        };
+        builder
+            .spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || {
+                    info_span!("zone with thread name", value = 1).in_scope(|| {});
+                });
+                let thread = std::thread::current();
+                let mut text = String::new();
+                super::write_thread_name(&mut text, &thread);
+                match name {
+                    Some(name) => assert_eq!(text, name),
+                    None => assert_eq!(text, format!("{:?}", thread.id())),
+                }
+            })
+            .unwrap()
+    });
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+test_config!(StartupInfoConfig {
+    fn startup_info(&self) -> bool {
+        true
+    }
+});
+
+fn startup_info() {
+    let summary = client::feature_summary();
+    assert_eq!(summary.contains("enable"), cfg!(feature = "enable"));
+    assert_eq!(
+        super::startup_info(),
+        format!("tracy-client features: {summary}")
+    );
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(StartupInfoConfig::default()));
+    tracing::subscriber::with_default(subscriber, || info!("after the startup info"));
+}
+
+fn duration_plots() {
+    let plotted = |name| {
+        super::PLOTS
+            .get()
+            .is_some_and(|p| p.read().unwrap().contains_key(name))
+    };
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(DurationPlotConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        for _ in 0..10 {
+            let span = info_span!("timed");
+            span.in_scope(|| span.in_scope(|| {}));
+            span.in_scope(|| {});
+        }
+        drop(info_span!("never entered"));
+    });
+    assert!(plotted("timed"));
+    assert!(!plotted("never entered"));
+}
+
+/// The callsite of the events with the static metadata declared by `event_metadata!`.
+struct EventCallsite(&'static tracing_core::Metadata<'static>);
+
+impl tracing_core::Callsite for EventCallsite {
+    fn set_interest(&self, _: tracing_core::Interest) {}
+    fn metadata(&self) -> &tracing_core::Metadata<'_> {
+        self.0
+    }
+}
+
+/// Declare the metadata of an event located at the given file and line, and its callsite.
+macro_rules! event_metadata {
+    ($callsite: ident, $metadata: ident, $file: expr, $line: expr) => {
+        static $callsite: EventCallsite = EventCallsite(&$metadata);
+        static $metadata: tracing_core::Metadata<'static> = tracing_core::Metadata::new(
+            "event",
+            "tests",
+            Level::INFO,
+            $file,
+            $line,
+            None,
+            tracing_core::field::FieldSet::new(&[], tracing_core::identify_callsite!(&$callsite)),
+            tracing_core::metadata::Kind::EVENT,
+        );
+    };
+}
+
+event_metadata!(LOCATED, LOCATED_EVENT, Some("events.rs"), Some(7));
+event_metadata!(UNLOCATED, UNLOCATED_EVENT, None, None);
+event_metadata!(FILE_ONLY, FILE_ONLY_EVENT, Some("events.rs"), None);
+event_metadata!(LINE_ONLY, LINE_ONLY_EVENT, None, Some(7));
+
+test_config!(EventLocationConfig {
+    fn event_location(&self) -> bool {
+        true
+    }
+});
+
+fn event_location() {
+    use tracing_core::Metadata;
+
+    let location = |metadata: &Metadata<'_>| {
+        let mut buf = String::new();
+        super::write_location(&mut buf, metadata);
+        buf
+    };
+
+    let span = info_span!("located");
+    let prefix = location(span.metadata().unwrap());
+    assert!(prefix.starts_with(file!()) && prefix.ends_with(": "));
+    assert_eq!(prefix.matches(file!()).count(), 1);
+    assert_eq!(location(&LOCATED_EVENT), "events.rs:7: ");
+    assert_eq!(location(&FILE_ONLY_EVENT), "events.rs: ");
+    assert_eq!(location(&LINE_ONLY_EVENT), "");
+    assert_eq!(location(&UNLOCATED_EVENT), "");
+
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(EventLocationConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        info!("located message");
+        info!(value = 42);
+    });
+}
+
+test_config!(ValuesOnlyConfig {
+    fn format_event_field(
+        &self,
+        dest: &mut String,
+        field: &tracing::field::Field,
+        value: &dyn std::fmt::Debug,
+    ) {
+        use std::fmt::Write;
+        if field.name() != "secret" {
+            let _ = write!(dest, "{value:?}");
+        }
+    }
+});
+
+test_config!(MemoryEventsConfig {
+    fn memory_events(&self) -> bool {
+        true
+    }
+});
+
+fn memory_events() {
+    use tracing::field::Visit;
+    let span = info_span!(
+        "fields",
+        tracy.alloc_pool = "pool",
+        tracy.alloc_ptr = 1,
+        tracy.alloc_bytes = 2,
+        tracy.free_ptr = 3,
+        other = 4,
+    );
+    let fields = span.metadata().unwrap().fields();
+    let field = |name: &str| fields.field(name).unwrap();
+    let config = MemoryEventsConfig::default();
+    let mut dest = String::new();
+    let mut visitor = super::TracyEventFieldVisitor {
+        dest: &mut dest,
+        frame_mark: false,
+        first: true,
+        config: &config,
+        plot: None,
+        memory: Some(super::MemoryEvent::default()),
+    };
+    visitor.record_str(&field("tracy.alloc_pool"), "textures");
+    visitor.record_i64(&field("tracy.alloc_ptr"), 0x1000);
+    visitor.record_u64(&field("tracy.alloc_bytes"), 1024);
+    visitor.record_i64(&field("other"), 4);
+    let memory = visitor.memory.take().unwrap();
+    assert!(memory.pool.is_some());
+    assert_eq!(memory.alloc_ptr, Some(0x1000));
+    assert_eq!(memory.alloc_bytes, Some(1024));
+    assert_eq!(memory.free_ptr, None);
+    assert_eq!(
+        dest, "other = 4",
+        "reserved fields are not part of the message"
+    );
+
+    let mut dest = String::new();
+    let mut visitor = super::TracyEventFieldVisitor {
+        dest: &mut dest,
+        frame_mark: false,
+        first: true,
+        config: &config,
+        plot: None,
+        memory: Some(super::MemoryEvent::default()),
+    };
+    visitor.record_i64(&field("tracy.free_ptr"), 0x1000);
+    let memory = visitor.memory.take().unwrap();
+    assert_eq!(memory.free_ptr, Some(0x1000));
+    assert!(memory.pool.is_none() && memory.alloc_ptr.is_none());
+    assert!(visitor.first);
+
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(config));
+    tracing::subscriber::with_default(subscriber, || {
+        for ptr in 1..=4 {
+            info!(
+                tracy.alloc_pool = "textures",
+                tracy.alloc_ptr = ptr,
+                tracy.alloc_bytes = 1024
+            );
+        }
+        info!(
+            tracy.alloc_ptr = 5,
+            tracy.alloc_bytes = 64,
+            "unpooled allocation"
+        );
+        for ptr in 1..=4 {
+            info!(tracy.alloc_pool = "textures", tracy.free_ptr = ptr);
+        }
+        info!(tracy.free_ptr = 5);
+    });
+}
+
+fn event_field_formatter() {
+    use tracing::field::Visit;
+    let span = info_span!(
+        "fields",
+        secret = 1,
+        a = 2,
+        b = "three",
+        tracy.frame_mark = true
+    );
+    let fields = span.metadata().unwrap().fields();
+    let field = |name: &str| fields.field(name).unwrap();
+
+    fn record<C: Config>(config: &C, field: impl Fn(&str) -> tracing::field::Field) -> String {
+        let mut dest = String::new();
+        let mut visitor = super::TracyEventFieldVisitor {
+            dest: &mut dest,
+            frame_mark: false,
+            first: true,
+            config,
+            plot: None,
+            memory: None,
+        };
+        visitor.record_u64(&field("secret"), 1);
+        visitor.record_i64(&field("a"), 2);
+        visitor.record_str(&field("b"), "three");
+        visitor.record_bool(&field("tracy.frame_mark"), true);
+        assert!(visitor.frame_mark);
+        dest
+    }
+    let default = record(&DefaultConfig::default(), field);
+    assert_eq!(default, "secret = 1, a = 2, b = three");
+    assert_eq!(record(&ValuesOnlyConfig::default(), field), "2, three");
+
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(ValuesOnlyConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        info!(secret = 1, "formatted message");
+        info!(secret = 1);
+    });
+}
+
+fn span_frame_mark() {
+    use tracing_subscriber::registry::{LookupSpan, Registry};
+    let marked = |span: &tracing::Span| {
+        let id = span.id().unwrap();
+        tracing::dispatcher::get_default(|dispatch| {
+            let registry = dispatch.downcast_ref::<Registry>().unwrap();
+            let span = registry.span(&id).unwrap();
+            let marked = span.extensions().get::<super::FrameMark>().is_some();
+            marked
+        })
+    };
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::default());
+    tracing::subscriber::with_default(subscriber, || {
+        for _ in 0..10 {
+            let frame = info_span!("frame", tracy.frame_mark = true);
+            frame.in_scope(|| info_span!("work").in_scope(|| {}));
+            assert!(marked(&frame));
+        }
+        let later = info_span!("later", tracy.frame_mark = tracing::field::Empty);
+        assert!(!marked(&later));
+        later.record("tracy.frame_mark", true);
+        assert!(marked(&later));
+        let unmarked = info_span!("unmarked", tracy.frame_mark = false);
+        assert!(!marked(&unmarked));
+        assert!(!marked(&info_span!("plain")));
+    });
+}
+
+test_config!(FrameImageConfig(Counter, Counter) {
+    fn frame_image(&self) -> Option<(Vec<u8>, u16, u16)> {
+        match self.1.fetch_add(1, std::sync::atomic::Ordering::Relaxed) {
+            0 => Some((vec![0x7F; 64 * 32 * 4], 64, 32)),
+            1 => Some((vec![0x7F; 64 * 32 * 4 - 1], 64, 32)),
+            2 => Some((vec![0x7F; 30 * 30 * 4], 30, 30)),
+            3 => Some((Vec::new(), 0, 0)),
+            _ => None,
+        }
+    }
+    fn on_error(&self, client: &client::Client, error: &'static str) {
+        self.2.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.0.on_error(client, error);
+    }
+});
+
+fn frame_image() {
+    let config = FrameImageConfig::default();
+    let (images, errors) = (
+        std::sync::Arc::clone(&config.1),
+        std::sync::Arc::clone(&config.2),
+    );
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::new(config));
+    tracing::subscriber::with_default(subscriber, || {
+        info!(tracy.frame_mark = true);
+        assert_eq!(images.load(std::sync::atomic::Ordering::Relaxed), 1);
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 0);
+        info_span!("frame", tracy.frame_mark = true).in_scope(|| {});
+        assert_eq!(images.load(std::sync::atomic::Ordering::Relaxed), 2);
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 1);
+        info!("not a frame");
+        info!(tracy.frame_mark = true);
+        assert_eq!(images.load(std::sync::atomic::Ordering::Relaxed), 3);
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 2);
+        info!(tracy.frame_mark = true);
+        assert_eq!(images.load(std::sync::atomic::Ordering::Relaxed), 4);
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 3);
+        info!(tracy.frame_mark = true);
+        assert_eq!(images.load(std::sync::atomic::Ordering::Relaxed), 5);
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 3);
+    });
+}
+
+fn span_callstack_depth() {
+    use tracing_subscriber::registry::{LookupSpan, Registry};
+    let depth = |span: &tracing::Span| {
+        let id = span.id().unwrap();
+        tracing::dispatcher::get_default(|dispatch| {
+            let registry = dispatch.downcast_ref::<Registry>().unwrap();
+            let span = registry.span(&id).unwrap();
+            let depth = span
+                .extensions()
+                .get::<super::CallstackDepth>()
+                .map(|d| d.0);
+            depth
+        })
+    };
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::default());
+    tracing::subscriber::with_default(subscriber, || {
+        let parent = info_span!("parent");
+        let _parent = parent.enter();
+        let deep = info_span!("deep", tracy.callstack_depth = 16);
+        let sibling = info_span!("sibling");
+        deep.in_scope(|| {});
+        sibling.in_scope(|| {});
+        assert_eq!(depth(&deep), Some(16));
+        assert_eq!(depth(&sibling), None);
+        assert_eq!(
+            depth(&info_span!("huge", tracy.callstack_depth = 100_000)),
+            Some(u16::MAX)
+        );
+        assert_eq!(
+            depth(&info_span!("negative", tracy.callstack_depth = -1)),
+            Some(0)
+        );
+        let later = info_span!("later", tracy.callstack_depth = tracing::field::Empty);
+        assert_eq!(depth(&later), None);
+        later.record("tracy.callstack_depth", 8u64);
+        assert_eq!(depth(&later), Some(8));
+        later.in_scope(|| {});
+    });
+}
+
+test_config!(LazyTextConfig {
+    fn lazy_text(&self) -> bool {
+        true
+    }
+});
+
+fn lazy_text() {
+    use tracing_subscriber::registry::{LookupSpan, Registry};
+    fn formatted<C: Config + 'static>(span: &tracing::Span) -> bool {
+        let id = span.id().unwrap();
+        tracing::dispatcher::get_default(|dispatch| {
+            let registry = dispatch.downcast_ref::<Registry>().unwrap();
+            let span = registry.span(&id).unwrap();
+            let formatted = span.extensions().get::<super::TracyFields<C>>().is_some();
+            formatted
+        })
+    }
+    assert!(!client::Client::running().unwrap().is_connected());
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(LazyTextConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("lazy", value = 1, later = tracing::field::Empty);
+        span.record("later", 2);
+        assert!(!formatted::<LazyTextConfig>(&span));
+        let id = span.id().unwrap().into_u64();
+        span.in_scope(|| assert_eq!(stack_ids().last(), Some(&id)));
+    });
+    let subscriber = tracing_subscriber::registry().with(TracyLayer::default());
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("eager", value = 1);
+        assert!(formatted::<DefaultConfig>(&span));
+    });
+}
+
+test_config!(SpanMemoryPoolsConfig {
+    fn span_memory_pools(&self) -> bool {
+        true
+    }
+});
+
+fn span_memory_pools() {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    let allocator = client::ProfiledAllocator::with_thread_pools(System, 0);
+    let client = client::Client::running().unwrap();
+    let pool = || client.thread_memory_pool();
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(SpanMemoryPoolsConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        let layout = Layout::new::<[u64; 4]>();
+        let outer = info_span!("outer phase");
+        let inner = info_span!("inner phase");
+        outer.in_scope(|| {
+            assert!(pool() == Some(super::pool_name("outer phase")));
+            let memory = inner.in_scope(|| {
+                assert!(pool() == Some(super::pool_name("inner phase")));
+                unsafe { allocator.alloc(layout) }
+            });
+            assert!(pool() == Some(super::pool_name("outer phase")));
+            unsafe { allocator.dealloc(memory, layout) };
+        });
+        assert!(pool().is_none());
+    });
+}
+
+test_config!(EventZonesConfig {
+    fn event_zones(&self) -> bool {
+        true
+    }
+});
+
+fn event_zones() {
+    assert_eq!(super::source_file_line(&LOCATED_EVENT), ("events.rs", 7));
+    assert_eq!(super::source_file_line(&FILE_ONLY_EVENT), ("events.rs", 0));
+    assert_eq!(
+        super::source_file_line(&LINE_ONLY_EVENT),
+        ("<not available>", 7)
+    );
+    assert_eq!(
+        super::source_file_line(&UNLOCATED_EVENT),
+        ("<not available>", 0)
+    );
+    let subscriber =
+        tracing_subscriber::registry().with(TracyLayer::new(EventZonesConfig::default()));
+    tracing::subscriber::with_default(subscriber, || {
+        for metadata in [&LOCATED_EVENT, &UNLOCATED_EVENT] {
+            tracing_core::Event::dispatch(metadata, &metadata.fields().value_set(&[]));
+            let locations = super::LOCATIONS.get().unwrap().read().unwrap();
+            assert!(locations.contains_key(&(metadata.callsite(), false)));
+        }
+        info!("event with a zone");
+    });
+}
+
+#[cfg(feature = "log")]
+fn log_records() {
+    use log::Log;
+    assert_eq!(
+        crate::logger::level_color(log::Level::Error),
+        Some(0xFF0000FF)
+    );
+    assert_eq!(
+        crate::logger::level_color(log::Level::Warn),
+        Some(0xFFA500FF)
+    );
+    assert_eq!(crate::logger::level_color(log::Level::Debug), None);
+
+    let logger = crate::TracyLogger::new(log::LevelFilter::Info);
+    let record = |level, message: &str| {
+        logger.log(
+            &log::Record::builder()
+                .level(level)
+                .target("tests")
+                .args(format_args!("{message}"))
+                .build(),
+        );
+    };
+    assert!(logger.enabled(&log::Metadata::builder().level(log::Level::Error).build()));
+    assert!(!logger.enabled(&log::Metadata::builder().level(log::Level::Debug).build()));
+    record(log::Level::Error, "red log record");
+    record(log::Level::Debug, "filtered log record");
+    record(log::Level::Info, &"too long log record ".repeat(4 * 1024));
+    // The records are formatted into the buffers reused by the layer.
+    record(log::Level::Info, &"reused buffer ".repeat(100));
+    assert!(crate::CACHE.with(|cache| cache.acquire().capacity() >= 1400));
+}
+
+/// Must run before anything else starts the client.
+fn new_manual() {
+    let config = ErrorCountingConfig::default();
+    let errors = std::sync::Arc::clone(&config.1);
+    let layer = TracyLayer::new_manual(config);
+    if cfg!(feature = "manual-lifetime") {
+        assert!(layer.client().is_none());
+    }
+    let subscriber = tracing_subscriber::registry().with(layer);
+    let dispatch = tracing::Dispatch::new(subscriber);
+    let report = || {
+        let outer = info_span!("manual outer", tracy.frame_mark = true);
+        let inner = info_span!("manual inner", value = 1);
+        let outer_entry = outer.enter();
+        let inner_entry = inner.enter();
+        drop(outer_entry);
+        drop(inner_entry);
+        info!(tracy.frame_mark = true, "manual message");
+    };
+    tracing::dispatcher::with_default(&dispatch, report);
+    if cfg!(feature = "manual-lifetime") {
+        assert!(
+            !client::Client::is_running(),
+            "the layer must not start the client"
+        );
+        assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), 0);
+    }
+    let before = errors.load(std::sync::atomic::Ordering::Relaxed);
+    tracing::dispatcher::with_default(&dispatch, || {
+        // Entered before the client starts, and exited after.
+        let span = info_span!("manual straddling");
+        let outer_entry = span.enter();
+        let _client = client::Client::start();
+        let inner_entry = span.enter();
+        drop(inner_entry);
+        drop(outer_entry);
+        let _entry = span.enter();
+    });
+    assert_eq!(errors.load(std::sync::atomic::Ordering::Relaxed), before);
+}
+
 pub(crate) fn test() {
+    new_manual();
     tracing::subscriber::set_global_default(
         tracing_subscriber::registry().with(TracyLayer::default()),
     )
@@ -112,11 +1264,48 @@ pub(crate) fn test() {
     it_works();
     it_works_2();
     multiple_entries();
+    zone_per_entry();
     out_of_order();
+    out_of_order_reentered();
+    reentered_span();
+    exit_without_enter();
+    diagnostic_color();
+    zone_name_cached();
+    record_messages();
     exit_in_different_thread();
     message_too_long();
     long_span_data();
+    span_target();
+    empty_span_fields();
     span_with_fields();
+    level_colors();
+    record_fields();
+    fibers();
+    span_filter();
+    min_level();
+    custom_formatter();
+    default_config_inference();
+    interned_locations();
+    auto_plot();
+    thread_naming();
+    message_split();
+    message_rate_limit();
+    thread_name_in_zone();
+    parent_annotation();
+    verbose_names();
+    startup_info();
+    duration_plots();
+    event_location();
+    event_field_formatter();
+    memory_events();
+    span_frame_mark();
+    span_callstack_depth();
+    lazy_text();
+    span_memory_pools();
+    event_zones();
+    frame_image();
+    #[cfg(feature = "log")]
+    log_records();
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
@@ -124,17 +1313,11 @@ pub(crate) fn test() {
     runtime.block_on(async_futures());
 }
 
-#[derive(Default)]
-struct CallstackConfig(DefaultConfig);
-impl Config for CallstackConfig {
-    type Formatter = <DefaultConfig as Config>::Formatter;
-    fn formatter(&self) -> &Self::Formatter {
-        self.0.formatter()
-    }
+test_config!(CallstackConfig {
     fn stack_depth(&self, _: &tracing_core::Metadata<'_>) -> u16 {
         100
     }
-}
+});
 
 fn benchmark_span(c: &mut Criterion) {
     c.bench_function("span/callstack", |bencher| {
@@ -159,6 +1342,18 @@ fn benchmark_span(c: &mut Criterion) {
     });
 }
 
+fn benchmark_reenter(c: &mut Criterion) {
+    c.bench_function("span/reenter", |bencher| {
+        let layer = tracing_subscriber::registry().with(TracyLayer::default());
+        tracing::subscriber::with_default(layer, || {
+            let span = tracing::error_span!("message", field1 = "first", field2 = "second");
+            bencher.iter(|| {
+                let _entered = span.enter();
+            });
+        });
+    });
+}
+
 fn benchmark_message(c: &mut Criterion) {
     c.bench_function("event/callstack", |bencher| {
         let layer =
@@ -181,7 +1376,12 @@ fn benchmark_message(c: &mut Criterion) {
 }
 
 pub(crate) fn bench() {
-    criterion::criterion_group!(benches, benchmark_span, benchmark_message);
+    criterion::criterion_group!(
+        benches,
+        benchmark_span,
+        benchmark_reenter,
+        benchmark_message
+    );
     benches();
     Criterion::default().configure_from_args().final_summary();
 }
@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::path::PathBuf;
 
 fn link_dependencies() {
     match std::env::var("CARGO_CFG_TARGET_OS").as_deref() {
@@ -65,19 +66,191 @@ fn set_feature_defines(mut c: cc::Build) -> cc::Build {
     c
 }
 
+/// The directory containing the Tracy client sources.
+fn tracy_client_source() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=TRACY_CLIENT_SYS_SOURCE");
+    let Some(source) = std::env::var_os("TRACY_CLIENT_SYS_SOURCE") else {
+        // Printing any `rerun-if` line disables the default of rerunning on any change to the
+        // package, so the bundled sources must be watched explicitly.
+        println!("cargo:rerun-if-changed=tracy");
+        return "tracy".into();
+    };
+    let source = PathBuf::from(source);
+    if !source.join("TracyClient.cpp").is_file() {
+        writeln!(
+            ::std::io::stderr(),
+            "TRACY_CLIENT_SYS_SOURCE=`{}` does not contain a `TracyClient.cpp` file!",
+            source.display(),
+        )
+        .expect("could not report the error");
+        ::std::process::exit(0xfd);
+    }
+    println!("cargo:rerun-if-changed={}", source.display());
+    source
+}
+
+/// Generate the bindings from the headers in `source`, as `make_sys.sh` does for the bindings
+/// included with this crate.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(source: &std::path::Path) {
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    let builder = || {
+        bindgen::Builder::default()
+            .header(source.join("tracy/TracyC.h").display().to_string())
+            .disable_header_comment()
+            .clang_arg("-DTRACY_ENABLE")
+    };
+    let generate = |builder: bindgen::Builder, file: &str| {
+        let bindings = builder
+            .generate()
+            .expect("could not generate the Tracy bindings")
+            .to_string();
+        // The space after type avoids hitting members called "type".
+        let bindings = bindings.replace("pub type ", "type ");
+        std::fs::write(out_dir.join(file), bindings).expect("could not write the Tracy bindings");
+    };
+    generate(
+        builder()
+            .allowlist_function(".*[Tt][Rr][Aa][Cc][Yy].*")
+            .allowlist_type(".*[Tt][Rr][Aa][Cc][Yy].*"),
+        "generated.rs",
+    );
+    generate(
+        builder()
+            .allowlist_function("___tracy_startup_profiler")
+            .allowlist_function("___tracy_shutdown_profiler")
+            .clang_arg("-DTRACY_MANUAL_LIFETIME"),
+        "generated_manual_lifetime.rs",
+    );
+    generate(
+        builder()
+            .allowlist_function("___tracy_fiber_enter")
+            .allowlist_function("___tracy_fiber_leave")
+            .clang_arg("-DTRACY_FIBERS"),
+        "generated_fibers.rs",
+    );
+}
+
+/// Read the `Name = 123` enumerator value out of a Tracy header.
+fn header_value(source: &str, name: &str) -> u32 {
+    let needle = format!("{name} = ");
+    let start = source
+        .find(&needle)
+        .expect("the Tracy header declares the value")
+        + needle.len();
+    let digits = source[start..]
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .unwrap_or_default();
+    digits.parse().expect("the Tracy header value is a number")
+}
+
+/// The version and protocol version of the Tracy sources in `source`, which the client is built
+/// from and, with the `bindgen` feature, the bindings are generated from.
+///
+/// These are written out for `src/lib.rs` to include, so that they follow the sources as they get
+/// updated or replaced with `TRACY_CLIENT_SYS_SOURCE`.
+fn source_version(source: &std::path::Path) -> ([u32; 3], u32) {
+    let read = |file: &str| {
+        std::fs::read_to_string(source.join("common").join(file))
+            .expect("could not read the Tracy version headers")
+    };
+    let version = read("TracyVersion.hpp");
+    let protocol = read("TracyProtocol.hpp");
+    let version = ["Major", "Minor", "Patch"].map(|name| header_value(&version, name));
+    (version, header_value(&protocol, "ProtocolVersion"))
+}
+
+fn write_version([major, minor, patch]: [u32; 3], protocol: u32) {
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    let source = format!(
+        "/// The version of the Tracy sources this crate is built from, as `(major, minor, patch)`.\n\
+         pub const TRACY_VERSION: (u32, u32, u32) = ({major}, {minor}, {patch});\n\
+         /// The version of the protocol the Tracy client speaks with the profiler.\n\
+         pub const PROTOCOL_VERSION: u32 = {protocol};\n"
+    );
+    std::fs::write(out_dir.join("version.rs"), source).expect("could not write the Tracy version");
+}
+
+/// Warn if `TRACY_CLIENT_VERSION` says the externally linked library is of another Tracy version
+/// than the sources this crate is built with.
+///
+/// Only the components present are compared, so `0.10` matches any `0.10.x` release.
+fn check_external_version(expected: [u32; 3]) {
+    println!("cargo:rerun-if-env-changed=TRACY_CLIENT_VERSION");
+    let Ok(version) = std::env::var("TRACY_CLIENT_VERSION") else {
+        return;
+    };
+    let [major, minor, patch] = expected;
+    let matches = version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .zip(expected)
+        .all(|(component, expected)| component.parse() == Ok(expected));
+    if !matches {
+        println!(
+            "cargo:warning=TRACY_CLIENT_VERSION=`{version}` does not match the Tracy \
+             v{major}.{minor}.{patch} sources tracy-client-sys is built with! \
+             The linked library may be incompatible with them."
+        );
+    }
+}
+
+fn cxx_standard() -> Option<String> {
+    const SUPPORTED: [&str; 4] = ["c++11", "c++14", "c++17", "c++20"];
+    println!("cargo:rerun-if-env-changed=TRACY_CLIENT_SYS_CXXSTD");
+    let standard = std::env::var("TRACY_CLIENT_SYS_CXXSTD").ok()?;
+    if !SUPPORTED.contains(&standard.as_str()) {
+        writeln!(
+            ::std::io::stderr(),
+            "TRACY_CLIENT_SYS_CXXSTD=`{standard}` is not supported! Expected one of {SUPPORTED:?}.",
+        )
+        .expect("could not report the error");
+        ::std::process::exit(0xfd);
+    }
+    Some(standard)
+}
+
+/// Reject the targets Tracy cannot be built for with a clear error, rather than a compiler one.
+fn check_target() {
+    let family = std::env::var("CARGO_CFG_TARGET_FAMILY").unwrap_or_default();
+    let os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if family.split(',').any(|family| family == "wasm") && os != "emscripten" {
+        writeln!(
+            ::std::io::stderr(),
+            "The Tracy client cannot be built for target_os=`{os}` on WebAssembly, only \
+             `emscripten` is supported! Disable the `enable` feature for this target.",
+        )
+        .expect("could not report the error");
+        ::std::process::exit(0xfd);
+    }
+}
+
 fn build_tracy_client() {
     if std::env::var_os("CARGO_FEATURE_ENABLE").is_some() {
+        check_target();
         let mut builder = set_feature_defines(cc::Build::new());
         let _ = builder
-            .file("tracy/TracyClient.cpp")
+            .file(tracy_client_source().join("TracyClient.cpp"))
             .warnings(false)
             .cpp(true);
+        let standard = cxx_standard();
         if let Ok(tool) = builder.try_get_compiler() {
             if tool.is_like_gnu() || tool.is_like_clang() {
                 // https://github.com/rust-lang/cc-rs/issues/855
-                builder.flag("-std=c++11");
+                let flag = format!("-std={}", standard.as_deref().unwrap_or("c++11"));
+                builder.flag(flag.as_str());
+            } else if tool.is_like_msvc() {
+                // MSVC defaults to C++14 and has no flag for the older standards.
+                if let Some(standard) = standard.filter(|s| s != "c++11") {
+                    builder.flag(format!("/std:{standard}").as_str());
+                }
             }
         }
+        if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("emscripten") {
+            builder.flag("-pthread");
+        }
         let _ = builder.try_flags_from_environment("TRACY_CLIENT_SYS_CXXFLAGS");
         builder.compile("libtracy-client.a");
         link_dependencies();
@@ -85,7 +258,22 @@ fn build_tracy_client() {
 }
 
 fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "bindgen")]
+    if std::env::var_os("CARGO_FEATURE_ENABLE").is_some() {
+        generate_bindings(&tracy_client_source());
+    }
+    for var in [
+        "TRACY_CLIENT_LIB",
+        "TRACY_CLIENT_LIB_PATH",
+        "TRACY_CLIENT_STATIC",
+    ] {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+    let (version, protocol) = source_version(&tracy_client_source());
+    write_version(version, protocol);
     if let Ok(lib) = std::env::var("TRACY_CLIENT_LIB") {
+        check_external_version(version);
         if let Ok(lib_path) = std::env::var("TRACY_CLIENT_LIB_PATH") {
             println!("cargo:rustc-link-search=native={lib_path}");
         }
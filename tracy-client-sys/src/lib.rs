@@ -26,7 +26,44 @@
 //! * `delayed-init` – profiler data is gathered into one structure and initialized on the first
 //!   request rather than statically at the DLL load at the expense of atomic load on each request
 //!   to the profiler data. Corresponds to the `TRACY_DELAYED_INIT` define.
+//! * `bindgen` – generate the bindings from the Tracy headers at build time rather than using the
+//!   bindings included with this crate. Useful in combination with `TRACY_CLIENT_SYS_SOURCE` (see
+//!   below). Requires `libclang`.
 #![doc = include_str!("../FEATURES.mkd")]
+//!
+//! # Build configuration
+//!
+//! The following environment variables are read by the build script:
+//!
+//! * `TRACY_CLIENT_LIB` – link to a prebuilt Tracy client library with this name instead of
+//!   building the bundled sources. `TRACY_CLIENT_LIB_PATH` adds a directory to the library search
+//!   path and a `TRACY_CLIENT_STATIC` value other than `0` links the library statically.
+//!   The library must be built from the same Tracy release as the bindings of this crate, see
+//!   [`TRACY_VERSION`]. Setting `TRACY_CLIENT_VERSION` to the version of the library, such as
+//!   `0.10.0` or `0.10`, makes the build script warn when it does not match.
+//! * `TRACY_CLIENT_SYS_SOURCE` – build the `TracyClient.cpp` from this directory instead of the
+//!   bundled Tracy sources. The sources must be compatible with the bindings of this crate.
+//! * `TRACY_CLIENT_SYS_CXXFLAGS` – additional flags to pass to the C++ compiler.
+//! * `TRACY_CLIENT_SYS_CXXSTD` – the C++ standard to build the client with. One of `c++11` (the
+//!   default), `c++14`, `c++17` or `c++20`.
+//!
+//! # Supported targets
+//!
+//! The bundled Tracy client can be built for Linux, Android, Windows, macOS, iOS, FreeBSD and
+//! DragonFly BSD. For WebAssembly, the client is only built for the `wasm32-unknown-emscripten`
+//! target, where it is compiled with `-pthread`, as Tracy runs threads of its own; the build fails
+//! with a descriptive error for other WebAssembly targets unless the `enable` feature is
+//! disabled.
+//!
+//! The emscripten target has not been verified end to end. Notably, link arguments of a library
+//! do not reach the final link of the binaries depending on it, so the binaries must be linked
+//! with `-pthread` as well, which their users have to set up themselves, e.g. with
+//!
+//! ```toml
+//! # .cargo/config.toml
+//! [target.wasm32-unknown-emscripten]
+//! rustflags = ["-C", "link-arg=-pthread"]
+//! ```
 #![allow(
     non_snake_case,
     non_camel_case_types,
@@ -36,17 +73,35 @@
 )]
 #![cfg_attr(tracy_client_sys_docs, feature(doc_auto_cfg))]
 
-#[cfg(feature = "enable")]
+include!(concat!(env!("OUT_DIR"), "/version.rs"));
+
+#[cfg(all(feature = "enable", not(feature = "bindgen")))]
 mod generated;
+#[cfg(all(feature = "enable", feature = "bindgen"))]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+}
 #[cfg(feature = "enable")]
 pub use generated::*;
 
-#[cfg(all(feature = "enable", feature = "manual-lifetime"))]
+#[cfg(all(
+    feature = "enable",
+    feature = "manual-lifetime",
+    not(feature = "bindgen")
+))]
 mod generated_manual_lifetime;
+#[cfg(all(feature = "enable", feature = "manual-lifetime", feature = "bindgen"))]
+mod generated_manual_lifetime {
+    include!(concat!(env!("OUT_DIR"), "/generated_manual_lifetime.rs"));
+}
 #[cfg(all(feature = "enable", feature = "manual-lifetime"))]
 pub use generated_manual_lifetime::*;
 
-#[cfg(all(feature = "enable", feature = "fibers"))]
+#[cfg(all(feature = "enable", feature = "fibers", not(feature = "bindgen")))]
 mod generated_fibers;
+#[cfg(all(feature = "enable", feature = "fibers", feature = "bindgen"))]
+mod generated_fibers {
+    include!(concat!(env!("OUT_DIR"), "/generated_fibers.rs"));
+}
 #[cfg(all(feature = "enable", feature = "fibers"))]
 pub use generated_fibers::{___tracy_fiber_enter, ___tracy_fiber_leave};
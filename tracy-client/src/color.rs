@@ -0,0 +1,110 @@
+/// A color of a zone, message or span location.
+///
+/// Tracy itself takes the colors as `0xRRGGBB`, while this crate has historically taken them as
+/// RGBA `u32`s, where the least significant 8 bits represent the (ignored) alpha component and the
+/// most significant 8 bits represent the red component. `Color` converts from such a `u32`, so
+/// the APIs accepting an `impl Into<Color>` continue to accept the plain RGBA integers as well.
+///
+/// A color with zero red, green and blue components leaves the choice of the color up to the
+/// profiler.
+///
+/// # Examples
+///
+/// ```
+/// use tracy_client::Color;
+/// assert_eq!(Color::rgb(0xFF, 0x80, 0x00), Color::from(0xFF8000FF));
+/// assert_eq!(Color::RED.to_tracy_u32(), 0xFF0000);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color(u32);
+
+impl Color {
+    /// Red.
+    pub const RED: Self = Self::rgb(0xFF, 0x00, 0x00);
+    /// Green.
+    pub const GREEN: Self = Self::rgb(0x00, 0xFF, 0x00);
+    /// Blue.
+    pub const BLUE: Self = Self::rgb(0x00, 0x00, 0xFF);
+    /// Yellow.
+    pub const YELLOW: Self = Self::rgb(0xFF, 0xFF, 0x00);
+    /// Cyan.
+    pub const CYAN: Self = Self::rgb(0x00, 0xFF, 0xFF);
+    /// Magenta.
+    pub const MAGENTA: Self = Self::rgb(0xFF, 0x00, 0xFF);
+    /// Orange.
+    pub const ORANGE: Self = Self::rgb(0xFF, 0xA5, 0x00);
+    /// White.
+    pub const WHITE: Self = Self::rgb(0xFF, 0xFF, 0xFF);
+    /// Leaves the choice of the color up to the profiler.
+    pub const DEFAULT: Self = Self::rgba(0, 0, 0, 0);
+
+    /// An opaque color with the given red, green and blue components.
+    #[must_use]
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self::rgba(red, green, blue, 0xFF)
+    }
+
+    /// A color with the given red, green, blue and alpha components.
+    ///
+    /// Tracy does not support transparency, so the alpha component is ignored by the profiler.
+    #[must_use]
+    pub const fn rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Self(u32::from_be_bytes([red, green, blue, alpha]))
+    }
+
+    /// The color as an RGBA `u32`, as accepted by [`Color::from`].
+    #[must_use]
+    pub const fn to_rgba(self) -> u32 {
+        self.0
+    }
+
+    /// The color as the `0xRRGGBB` value expected by the Tracy C API.
+    #[must_use]
+    pub const fn to_tracy_u32(self) -> u32 {
+        self.0 >> 8
+    }
+}
+
+impl From<u32> for Color {
+    /// Interpret `rgba` as the red, green, blue and alpha components, most significant byte first.
+    fn from(rgba: u32) -> Self {
+        Self(rgba)
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((red, green, blue): (u8, u8, u8)) -> Self {
+        Self::rgb(red, green, blue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Color;
+
+    #[test]
+    fn round_trip() {
+        for rgba in [0xFF0000FF, 0x00FF00FF, 0x0000FFFF, 0x12345678, 0] {
+            let color = Color::from(rgba);
+            assert_eq!(color.to_rgba(), rgba);
+            assert_eq!(color.to_tracy_u32(), rgba >> 8);
+        }
+        assert_eq!(Color::rgb(0x12, 0x34, 0x56), Color::from(0x123456FF));
+        assert_eq!(Color::rgba(0x12, 0x34, 0x56, 0x78), Color::from(0x12345678));
+        assert_eq!(Color::from((0x12, 0x34, 0x56)).to_tracy_u32(), 0x123456);
+    }
+
+    #[test]
+    fn named_colors() {
+        assert_eq!(Color::RED.to_tracy_u32(), 0xFF0000);
+        assert_eq!(Color::GREEN.to_tracy_u32(), 0x00FF00);
+        assert_eq!(Color::BLUE.to_tracy_u32(), 0x0000FF);
+        assert_eq!(Color::YELLOW.to_tracy_u32(), 0xFFFF00);
+        assert_eq!(Color::CYAN.to_tracy_u32(), 0x00FFFF);
+        assert_eq!(Color::MAGENTA.to_tracy_u32(), 0xFF00FF);
+        assert_eq!(Color::ORANGE.to_tracy_u32(), 0xFFA500);
+        assert_eq!(Color::WHITE.to_tracy_u32(), 0xFFFFFF);
+        assert_eq!(Color::DEFAULT.to_tracy_u32(), 0);
+        assert_eq!(Color::RED, Color::from(0xFF0000FF));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::Client;
+
+/// A guard representing a fiber the current thread has entered.
+///
+/// Create with the [`Client::fiber`] function.
+pub struct Fiber {
+    client: Client,
+    _no_send_sync: std::marker::PhantomData<*mut ()>,
+}
+
+/// A name for a fiber.
+///
+/// Tracy identifies fibers by the address of their name, so the same `FiberName` should be used
+/// every time a particular fiber is entered.
+///
+/// Create with the [`fiber_name!`](crate::fiber_name) macro.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FiberName(pub(crate) &'static str);
+
+impl FiberName {
+    /// Construct a `FiberName` dynamically, leaking the provided String.
+    ///
+    /// You should call this function once for a given name, and store the returned `FiberName`
+    /// for continued use, to avoid rapid memory use growth. Whenever possible, prefer the
+    /// [`fiber_name!`](crate::fiber_name) macro, which takes a literal name and doesn't leak
+    /// memory.
+    ///
+    /// The resulting value may be used as an argument for the [`Client::fiber_enter`] and
+    /// [`Client::fiber`] methods.
+    #[must_use]
+    pub fn new_leak(name: String) -> Self {
+        #[cfg(feature = "enable")]
+        {
+            // Ensure the name is null-terminated.
+            let mut name = name;
+            name.push('\0');
+            // Drop excess capacity by converting into a boxed str, then leak.
+            let name = Box::leak(name.into_boxed_str());
+            Self(name)
+        }
+        #[cfg(not(feature = "enable"))]
+        {
+            drop(name);
+            Self("\0")
+        }
+    }
+}
+
+/// Instrumentation for fibers, coroutines and other units of work that migrate between threads.
+///
+/// These methods only have an effect when the `fibers` feature is enabled. Otherwise they compile
+/// down to no-ops.
+impl Client {
+    /// Indicate that the current thread has started executing the fiber named `name`.
+    ///
+    /// Any spans created on this thread until the matching [`Client::fiber_leave`] will be
+    /// attributed to the fiber rather than the thread. A fiber must be left before it can be
+    /// entered on another thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracy_client::fiber_name;
+    /// # let client = tracy_client::Client::start();
+    /// client.fiber_enter(fiber_name!("task 1"));
+    /// // ... poll the task ...
+    /// client.fiber_leave();
+    /// ```
+    #[cfg_attr(not(feature = "fibers"), allow(unused_variables))]
+    pub fn fiber_enter(&self, name: FiberName) {
+        #[cfg(all(feature = "enable", feature = "fibers"))]
+        unsafe {
+            // SAFE: We ensured that the name would be null-terminated and live forever.
+            let () = sys::___tracy_fiber_enter(name.0.as_ptr().cast());
+        }
+    }
+
+    /// Indicate that the current thread has stopped executing the fiber it last entered.
+    pub fn fiber_leave(&self) {
+        #[cfg(all(feature = "enable", feature = "fibers"))]
+        unsafe {
+            let () = sys::___tracy_fiber_leave();
+        }
+    }
+
+    /// Enter the fiber named `name` for the lifetime of the returned guard.
+    ///
+    /// Dropping the returned [`Fiber`] will leave the fiber.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracy_client::fiber_name;
+    /// # let client = tracy_client::Client::start();
+    /// {
+    ///     let _fiber = client.fiber(fiber_name!("task 1"));
+    ///     // ... poll the task ...
+    /// } // _fiber is left
+    /// ```
+    #[must_use]
+    pub fn fiber(&self, name: FiberName) -> Fiber {
+        self.fiber_enter(name);
+        Fiber {
+            client: self.clone(),
+            _no_send_sync: std::marker::PhantomData,
+        }
+    }
+}
+
+impl Drop for Fiber {
+    fn drop(&mut self) {
+        self.client.fiber_leave();
+    }
+}
+
+/// Construct a [`FiberName`].
+///
+/// The resulting value may be used as an argument for the [`Client::fiber_enter`] and
+/// [`Client::fiber`] methods. The macro can be used in a `const` context.
+#[macro_export]
+macro_rules! fiber_name {
+    ($name: literal) => {{
+        unsafe { $crate::internal::create_fiber_name(concat!($name, "\0")) }
+    }};
+}
@@ -44,6 +44,10 @@ impl FrameName {
 impl Client {
     /// Indicate that rendering of a continuous frame has ended.
     ///
+    /// Subsystems running at their own rate (e.g. a fixed-step physics loop next to a variable
+    /// rate render loop) should use [`Client::secondary_frame_mark`] instead, which keeps a
+    /// separate frame sequence for each name.
+    ///
     /// # Examples
     ///
     /// In a traditional rendering scenarios a frame mark should be inserted after a buffer swap.
@@ -67,6 +71,9 @@ impl Client {
 
     /// Indicate that rendering of a secondary (named) continuous frame has ended.
     ///
+    /// Each distinct `name` produces an independent frame sequence, and thus its own frame time
+    /// graph, in the profiler.
+    ///
     /// # Examples
     ///
     /// Much like with the primary frame mark, the secondary (named) frame mark should be inserted
@@ -94,7 +101,9 @@ impl Client {
 
     /// Indicate that a processing of a non-continuous frame has begun.
     ///
-    /// Dropping the returned [`Frame`] will terminate the non-continuous frame.
+    /// Dropping the returned [`Frame`] will terminate the non-continuous frame. This makes the
+    /// frame span exactly the scope the guard lives in, including on an early return or while
+    /// unwinding from a panic.
     ///
     /// # Examples
     ///
@@ -107,13 +116,98 @@ impl Client {
     /// ```
     #[must_use]
     pub fn non_continuous_frame(&self, name: FrameName) -> Frame {
+        self.frame_start(name);
+        Frame(self.clone(), name)
+    }
+
+    /// Indicate that a processing of a non-continuous frame has begun.
+    ///
+    /// Every call must be paired with a later call to [`Client::frame_end`] with the same `name`.
+    /// Frames with different names may overlap, but frames with the same name may not. Prefer
+    /// [`Client::non_continuous_frame`], which ends the frame automatically, whenever the frame
+    /// corresponds to a lexical scope.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracy_client::frame_name;
+    /// # let client = tracy_client::Client::start();
+    /// client.frame_start(frame_name!("loading"));
+    /// // ...
+    /// client.frame_end(frame_name!("loading"));
+    /// ```
+    pub fn frame_start(&self, name: FrameName) {
         #[cfg(feature = "enable")]
         unsafe {
             // SAFE: We ensure that the name would be null-terminated.
             let () = sys::___tracy_emit_frame_mark_start(name.0.as_ptr().cast());
         }
-        Frame(self.clone(), name)
     }
+
+    /// Indicate that a processing of a non-continuous frame started with [`Client::frame_start`]
+    /// has ended.
+    pub fn frame_end(&self, name: FrameName) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            // SAFE: We ensure that the name would be null-terminated.
+            let () = sys::___tracy_emit_frame_mark_end(name.0.as_ptr().cast());
+        }
+    }
+
+    /// Attach an image to the current frame.
+    ///
+    /// `rgba` must contain `width * height` pixels of 4 bytes each, in row-major order. Tracy
+    /// additionally requires both `width` and `height` to be non-zero multiples of 4. The image
+    /// data is copied, so the buffer may be reused as soon as this function returns.
+    ///
+    /// `offset` specifies how many frames ago the image was captured (e.g. if the image is read
+    /// back from the GPU with a couple frames of latency) and `flip` indicates that the image
+    /// should be flipped vertically.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the length of `rgba` does not match the specified dimensions, or
+    /// if either dimension is zero or not a multiple of 4.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let client = tracy_client::Client::start();
+    /// let screenshot = vec![0u8; 320 * 180 * 4];
+    /// tracy_client::Client::running()
+    ///     .expect("client must be running")
+    ///     .frame_image(&screenshot, 320, 180, 0, false);
+    /// ```
+    pub fn frame_image(&self, rgba: &[u8], width: u16, height: u16, offset: u8, flip: bool) {
+        assert_eq!(
+            rgba.len(),
+            usize::from(width) * usize::from(height) * 4,
+            "frame image buffer length does not match its dimensions",
+        );
+        // The compression of the image works on blocks of 4x4 pixels, and reads and writes out of
+        // bounds for any other dimensions.
+        assert!(
+            frame_image_dimensions_valid(width, height),
+            "frame image dimensions must be non-zero multiples of 4",
+        );
+        #[cfg(feature = "enable")]
+        unsafe {
+            // SAFE: We ensured the buffer is large enough for the dimensions, which consist of
+            // whole 4x4 blocks. Tracy copies the data before returning.
+            let () = sys::___tracy_emit_frame_image(
+                rgba.as_ptr().cast(),
+                width,
+                height,
+                offset,
+                flip.into(),
+            );
+        }
+    }
+}
+
+/// Whether Tracy can compress a frame image of `width` by `height` pixels.
+const fn frame_image_dimensions_valid(width: u16, height: u16) -> bool {
+    width != 0 && height != 0 && width % 4 == 0 && height % 4 == 0
 }
 
 /// Construct a [`FrameName`].
@@ -129,13 +223,7 @@ macro_rules! frame_name {
 
 impl Drop for Frame {
     fn drop(&mut self) {
-        #[cfg(feature = "enable")]
-        unsafe {
-            // SAFE: We ensure that thena me would be null-terminated. We also still have an owned
-            // Client handle.
-            let () = sys::___tracy_emit_frame_mark_end(self.1 .0.as_ptr().cast());
-            std::convert::identity(&self.0);
-        }
+        self.0.frame_end(self.1);
     }
 }
 
@@ -177,3 +265,33 @@ macro_rules! non_continuous_frame {
             .non_continuous_frame($crate::frame_name!($name))
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::Client;
+
+    #[test]
+    fn frame_image_accepts_whole_blocks() {
+        let client = Client::start();
+        client.frame_image(&[0; 4 * 4 * 4], 4, 4, 0, false);
+        client.frame_image(&[0; 8 * 4 * 4], 8, 4, 0, true);
+    }
+
+    #[test]
+    #[should_panic = "non-zero multiples of 4"]
+    fn frame_image_rejects_partial_blocks() {
+        Client::start().frame_image(&[0; 2 * 2 * 4], 2, 2, 0, false);
+    }
+
+    #[test]
+    #[should_panic = "non-zero multiples of 4"]
+    fn frame_image_rejects_empty_images() {
+        Client::start().frame_image(&[], 0, 4, 0, false);
+    }
+
+    #[test]
+    #[should_panic = "non-zero multiples of 4"]
+    fn frame_image_rejects_unaligned_dimensions() {
+        Client::start().frame_image(&[0; 30 * 30 * 4], 30, 30, 0, false);
+    }
+}
@@ -78,6 +78,8 @@ pub struct GpuContext {
     #[cfg(feature = "enable")]
     gpu_start_timestamp: i64,
     #[cfg(feature = "enable")]
+    calibrated: bool,
+    #[cfg(feature = "enable")]
     span_freelist: Arc<Mutex<Vec<u16>>>,
     _private: (),
 }
@@ -171,6 +173,36 @@ impl Client {
         ty: GpuContextType,
         gpu_timestamp: i64,
         period: f32,
+    ) -> Result<GpuContext, GpuContextCreationError> {
+        self.new_gpu_context_impl(name, ty, gpu_timestamp, period, false)
+    }
+
+    /// Creates a new GPU context that is periodically recalibrated with
+    /// [`GpuContext::calibrate`].
+    ///
+    /// Otherwise behaves exactly like [`Client::new_gpu_context`]. Tracy only accepts calibration
+    /// data for the contexts that announced it upon their creation.
+    ///
+    /// # Errors
+    ///
+    /// - If more than 255 contexts were made during the lifetime of the application.
+    pub fn new_calibrated_gpu_context(
+        self,
+        name: Option<&str>,
+        ty: GpuContextType,
+        gpu_timestamp: i64,
+        period: f32,
+    ) -> Result<GpuContext, GpuContextCreationError> {
+        self.new_gpu_context_impl(name, ty, gpu_timestamp, period, true)
+    }
+
+    fn new_gpu_context_impl(
+        self,
+        name: Option<&str>,
+        ty: GpuContextType,
+        gpu_timestamp: i64,
+        period: f32,
+        calibrated: bool,
     ) -> Result<GpuContext, GpuContextCreationError> {
         #[cfg(feature = "enable")]
         {
@@ -192,7 +224,8 @@ impl Client {
                     gpuTime: gpu_timestamp,
                     period,
                     context,
-                    flags: 0,
+                    // Corresponds to `GpuContextCalibration` in `TracyQueue.hpp`.
+                    flags: u8::from(calibrated),
                     type_: ty as u8,
                 });
             };
@@ -216,6 +249,7 @@ impl Client {
                 _client: self,
                 value: context,
                 gpu_start_timestamp: gpu_timestamp,
+                calibrated,
                 span_freelist: Arc::new(Mutex::new((0..=u16::MAX).collect())),
                 _private: (),
             })
@@ -238,6 +272,32 @@ impl GpuContext {
         Ok((start, end))
     }
 
+    /// Resynchronize the gpu clock of this context with the cpu clock.
+    ///
+    /// Clocks of the cpu and the gpu drift apart over time, which makes the gpu zones of long
+    /// captures gradually misalign with the cpu zones. Call this function right after each
+    /// calibration query completes, where `gpu_time` is the gpu timestamp obtained by the query
+    /// and `cpu_delta` is the cpu time in nanoseconds that elapsed since the previous
+    /// calibration (or since the creation of the context).
+    ///
+    /// Only contexts created with [`Client::new_calibrated_gpu_context`] are calibrated; this
+    /// function does nothing for other contexts. It also does nothing if the `enable` feature is
+    /// disabled.
+    pub fn calibrate(&self, gpu_time: i64, cpu_delta: i64) {
+        #[cfg(feature = "enable")]
+        if self.calibrated {
+            // SAFETY:
+            // - The context has been created with the calibration flag set.
+            unsafe {
+                sys::___tracy_emit_gpu_calibration_serial(sys::___tracy_gpu_calibration_data {
+                    gpuTime: gpu_time,
+                    cpuDelta: cpu_delta,
+                    context: self.value,
+                });
+            }
+        }
+    }
+
     /// Creates a new gpu span with the given source location.
     ///
     /// This should be called right next to where you record the corresponding gpu timestamp. This
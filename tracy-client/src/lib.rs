@@ -21,23 +21,41 @@
 //! As thus, you may want make sure to only enable the `tracy-client` crate conditionally, via
 //! the `enable` feature flag provided by this crate.
 //!
+//! With the `enable` feature disabled, the API of this crate remains identical in shape: every
+//! type, function and macro is still available, but they compile down to no-ops. The
+//! instrumentation can thus be left in place unconditionally.
+//!
 //! # Features
 //!
 //! The following crate features are provided to customize the functionality of the Tracy client:
 //!
 #![doc = include_str!("../FEATURES.mkd")]
+//!
+//! Additionally, the `allocator-api` feature implements the unstable `Allocator` trait for
+//! [`ProfiledAllocator`]. It requires a nightly compiler.
 #![cfg_attr(tracy_client_docs, feature(doc_auto_cfg))]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
+pub use crate::color::Color;
+pub use crate::fiber::{Fiber, FiberName};
 pub use crate::frame::{frame_mark, Frame, FrameName};
 pub use crate::gpu::{
     GpuContext, GpuContextCreationError, GpuContextType, GpuSpan, GpuSpanCreationError,
 };
-pub use crate::plot::PlotName;
-pub use crate::span::{Span, SpanLocation};
+pub use crate::plot::{PlotFormat, PlotName};
+pub use crate::span::{Span, SpanLocation, SpanLocationCache};
+#[cfg(feature = "manual-lifetime")]
+pub use crate::state::ClientOwner;
 use std::alloc;
+use std::cell::Cell;
 use std::ffi::CString;
+#[cfg(feature = "allocator-api")]
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering};
 pub use sys;
 
+mod color;
+mod fiber;
 mod frame;
 mod gpu;
 mod plot;
@@ -50,7 +68,6 @@ pub mod internal {
     pub use crate::{span::SpanLocation, sys};
     pub use once_cell::sync::Lazy;
     pub use std::any::type_name;
-    use std::ffi::CString;
     pub use std::ptr::null;
 
     #[inline(always)]
@@ -60,17 +77,18 @@ pub mod internal {
         span_name: *const u8,
         file: *const u8,
         line: u32,
+        color: crate::Color,
     ) -> SpanLocation {
         #[cfg(feature = "enable")]
         {
-            let function_name = CString::new(&type_name[..type_name.len() - 3]).unwrap();
+            let function_name = crate::truncated_c_string(&type_name[..type_name.len() - 3]);
             SpanLocation {
                 data: sys::___tracy_source_location_data {
                     name: span_name.cast(),
                     function: function_name.as_ptr(),
                     file: file.cast(),
                     line,
-                    color: 0,
+                    color: color.to_tracy_u32(),
                 },
                 _function_name: function_name,
             }
@@ -85,6 +103,18 @@ pub mod internal {
         crate::frame::FrameName(name)
     }
 
+    #[inline(always)]
+    #[must_use]
+    pub const unsafe fn create_fiber_name(name: &'static str) -> crate::fiber::FiberName {
+        crate::fiber::FiberName(name)
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub const unsafe fn create_pool_name(name: &'static str) -> crate::PoolName {
+        crate::PoolName(name)
+    }
+
     #[inline(always)]
     #[must_use]
     pub const unsafe fn create_plot(name: &'static str) -> crate::plot::PlotName {
@@ -114,9 +144,12 @@ pub mod internal {
 /// Depending on the build configuration, the client may collect and make available machine
 /// and source code of the application as well as other potentially sensitive information.
 ///
-/// When all of the `Client` values are dropped, the underlying Tracy client will be shut down as
-/// well. Shutting down the `Client` will discard any information gathered up to that point that
-/// still hasn't been delivered to the profiler application.
+/// Unless the `manual-lifetime` feature is enabled, the underlying Tracy client is started before
+/// `main` (or upon first use with `delayed-init`) and runs until the program exits, regardless of
+/// whether any `Client` values exist. With `manual-lifetime` the Tracy client is started by the
+/// first call to [`Client::start`] and is not shut down when the `Client` values are dropped,
+/// but only once the last owner returned by `Client::start_owned` is. See [`Client::start`] for
+/// details.
 pub struct Client(());
 
 /// Instrumentation methods for outputting events occurring at a specific instant.
@@ -128,6 +161,9 @@ impl Client {
     /// Specifying a non-zero `callstack_depth` will enable collection of callstack for this
     /// message. The number provided will limit the number of call frames collected. Note that
     /// enabling callstack collection introduces a non-trivial amount of overhead to this call.
+    /// With a `callstack_depth` of zero the stack is not walked at all.
+    ///
+    /// The [`message!`](crate::message!) macro is a convenience wrapper over this method.
     pub fn message(&self, message: &str, callstack_depth: u16) {
         #[cfg(feature = "enable")]
         unsafe {
@@ -137,42 +173,196 @@ impl Client {
         }
     }
 
+    /// Output a message, explicitly choosing whether a callstack is collected.
+    ///
+    /// A `callstack_depth` of zero emits a plain message without walking the stack at all, while
+    /// a non-zero depth collects a callstack of at most that many frames, regardless of
+    /// [`Client::default_stack_depth`].
+    pub fn message_with_callstack(&self, message: &str, callstack_depth: u16) {
+        match MessageCallstack::new(callstack_depth) {
+            MessageCallstack::None => self.message(message, 0),
+            MessageCallstack::Frames(depth) => self.message(message, depth),
+        }
+    }
+
     /// Output a message with an associated color.
     ///
     /// Specifying a non-zero `callstack_depth` will enable collection of callstack for this
     /// message. The number provided will limit the number of call frames collected. Note that
     /// enabling callstack collection introduces a non-trivial amount of overhead to this call.
     ///
-    /// The colour shall be provided as RGBA, where the least significant 8 bits represent the alpha
-    /// component and most significant 8 bits represent the red component.
-    pub fn color_message(&self, message: &str, rgba: u32, callstack_depth: u16) {
+    /// The colour may be provided as a [`Color`], or as an RGBA `u32`, where the least significant 8
+    /// bits represent the alpha component and most significant 8 bits represent the red component.
+    pub fn color_message(&self, message: &str, color: impl Into<Color>, callstack_depth: u16) {
         #[cfg(feature = "enable")]
         unsafe {
             let depth = adjust_stack_depth(callstack_depth).into();
             let () = sys::___tracy_emit_messageC(
                 message.as_ptr().cast(),
                 message.len(),
-                rgba >> 8,
+                color.into().to_tracy_u32(),
                 depth,
             );
         }
     }
+
+    /// Output a message with an associated color, explicitly choosing whether a callstack is
+    /// collected.
+    ///
+    /// The `callstack_depth` is interpreted as by [`Client::message_with_callstack`].
+    pub fn color_message_with_callstack(
+        &self,
+        message: &str,
+        color: impl Into<Color>,
+        callstack_depth: u16,
+    ) {
+        match MessageCallstack::new(callstack_depth) {
+            MessageCallstack::None => self.color_message(message, color, 0),
+            MessageCallstack::Frames(depth) => self.color_message(message, color, depth),
+        }
+    }
+
+    /// Output information about the application, such as its version or build configuration.
+    ///
+    /// Unlike regular messages, this information is presented by the profiler alongside the
+    /// other trace metadata, which makes it easy to later identify the build a trace came from.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let client = tracy_client::Client::start();
+    /// client.app_info(concat!("my-app ", env!("CARGO_PKG_VERSION")));
+    /// ```
+    pub fn app_info(&self, info: &str) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            let () = sys::___tracy_emit_message_appinfo(info.as_ptr().cast(), info.len());
+        }
+    }
+
+    /// Set the callstack depth used by the [`message!`](crate::message!) and
+    /// [`color_message!`](crate::color_message!) macros when no depth is specified.
+    ///
+    /// This allows choosing between the cheap messages and the messages with callstacks for the
+    /// entire process at once. The depth is clamped to the maximum supported by Tracy. Until
+    /// this is called, the default depth is zero and no callstacks are collected.
+    pub fn set_default_stack_depth(&self, callstack_depth: u16) {
+        DEFAULT_STACK_DEPTH.store(adjust_stack_depth(callstack_depth), Ordering::Relaxed);
+    }
+
+    /// The callstack depth set with [`Client::set_default_stack_depth`].
+    #[must_use]
+    pub fn default_stack_depth(&self) -> u16 {
+        DEFAULT_STACK_DEPTH.load(Ordering::Relaxed)
+    }
+}
+
+static DEFAULT_STACK_DEPTH: AtomicU16 = AtomicU16::new(0);
+
+/// How a message is emitted for a given callstack depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MessageCallstack {
+    /// Emitted as a plain message, without walking the stack.
+    None,
+    /// Emitted with a callstack of at most this many frames.
+    Frames(u16),
+}
+
+impl MessageCallstack {
+    const fn new(callstack_depth: u16) -> Self {
+        if callstack_depth == 0 {
+            Self::None
+        } else {
+            Self::Frames(adjust_stack_depth(callstack_depth))
+        }
+    }
+}
+
+/// Convenience macro for [`Client::message`] on the current client.
+///
+/// The callstack depth may be specified as the second argument, in which case the message is
+/// output with [`Client::message_with_callstack`], collecting a callstack only if the depth is
+/// non-zero. Otherwise the depth defaults to [`Client::default_stack_depth`], which is zero (no
+/// callstack is collected) unless changed.
+///
+/// # Panics
+///
+/// - If a `Client` isn't currently running.
+///
+/// # Example
+///
+/// ```rust
+/// # let _client = tracy_client::Client::start();
+/// tracy_client::message!("loading finished");
+/// tracy_client::message!("unexpected input", 16);
+/// ```
+#[macro_export]
+macro_rules! message {
+    ($message: expr) => {{
+        let client = $crate::Client::running().expect("message! without a running Client");
+        client.message($message, client.default_stack_depth())
+    }};
+    ($message: expr, $callstack_depth: expr) => {{
+        $crate::Client::running()
+            .expect("message! without a running Client")
+            .message_with_callstack($message, $callstack_depth)
+    }};
+}
+
+/// Convenience macro for [`Client::color_message`] on the current client.
+///
+/// The callstack depth may be specified as the third argument, in which case the message is
+/// output with [`Client::color_message_with_callstack`]. Otherwise the depth defaults to
+/// [`Client::default_stack_depth`].
+///
+/// # Panics
+///
+/// - If a `Client` isn't currently running.
+///
+/// # Example
+///
+/// ```rust
+/// # let _client = tracy_client::Client::start();
+/// tracy_client::color_message!("cache miss", 0xFFA500FF);
+/// tracy_client::color_message!("unexpected input", 0xFF0000FF, 16);
+/// tracy_client::color_message!("retrying", tracy_client::Color::YELLOW);
+/// ```
+#[macro_export]
+macro_rules! color_message {
+    ($message: expr, $rgba: expr) => {{
+        let client = $crate::Client::running().expect("color_message! without a running Client");
+        client.color_message($message, $rgba, client.default_stack_depth())
+    }};
+    ($message: expr, $rgba: expr, $callstack_depth: expr) => {{
+        $crate::Client::running()
+            .expect("color_message! without a running Client")
+            .color_message_with_callstack($message, $rgba, $callstack_depth)
+    }};
 }
 
 impl Client {
     /// Set the current thread name to the provided value.
     ///
-    /// # Panics
-    ///
-    /// This function will panic if the name contains interior null characters.
+    /// The name will be cut short at the first null character, if any.
     pub fn set_thread_name(&self, name: &str) {
         #[cfg(feature = "enable")]
         unsafe {
-            let name = CString::new(name).unwrap();
+            let name = truncated_c_string(name);
             // SAFE: `name` is a valid null-terminated string.
             internal::set_thread_name(name.as_ptr().cast());
         }
     }
+
+    /// Set the current thread name to the provided value, hinting the thread group it belongs to.
+    ///
+    /// Newer versions of Tracy group the threads with the same `group_hint` together in the
+    /// profiler, which is useful to e.g. collapse the threads of a thread pool. The version of
+    /// Tracy this crate is built against does not support hints, so the hint is currently ignored
+    /// and this behaves exactly like [`Client::set_thread_name`].
+    pub fn set_thread_name_with_hint(&self, name: &str, group_hint: i32) {
+        let _ = group_hint;
+        self.set_thread_name(name);
+    }
 }
 
 /// Convenience macro for [`Client::set_thread_name`] on the current client.
@@ -193,6 +383,104 @@ macro_rules! set_thread_name {
     }};
 }
 
+/// Convert `string` into a C string, cutting it short at the first null character, if any.
+fn truncated_c_string(string: &str) -> CString {
+    let string = string.split('\0').next().unwrap_or_default();
+    CString::new(string).unwrap_or_default()
+}
+
+/// Instrumentation methods for memory (de)allocations made outside of a [`ProfiledAllocator`].
+impl Client {
+    /// Record an allocation of `size` bytes at `address`.
+    ///
+    /// This makes manually accounted memory, such as the contents of a custom arena, visible in
+    /// the memory view of the profiler. The `address` only identifies the allocation for the
+    /// matching [`Client::memory_free`] and is never dereferenced. Allocations in a named `pool`
+    /// are presented separately from the unnamed ones, see [`ProfiledAllocator::new_named`].
+    ///
+    /// Specifying a non-zero `callstack_depth` will enable collection of callstack for this
+    /// allocation. The number provided will limit the number of call frames collected.
+    pub fn memory_alloc(
+        &self,
+        pool: Option<PoolName>,
+        address: usize,
+        size: usize,
+        callstack_depth: u16,
+    ) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            let ptr = address as *const std::ffi::c_void;
+            let depth = adjust_stack_depth(callstack_depth).into();
+            match (pool, depth) {
+                (None, 0) => sys::___tracy_emit_memory_alloc(ptr, size, 1),
+                (None, _) => sys::___tracy_emit_memory_alloc_callstack(ptr, size, depth, 1),
+                // SAFE: We made sure the pool name is null-terminated and lives forever.
+                (Some(pool), 0) => {
+                    let name = pool.0.as_ptr().cast();
+                    sys::___tracy_emit_memory_alloc_named(ptr, size, 1, name);
+                }
+                (Some(pool), _) => {
+                    let name = pool.0.as_ptr().cast();
+                    sys::___tracy_emit_memory_alloc_callstack_named(ptr, size, depth, 1, name);
+                }
+            }
+        }
+    }
+
+    /// Record the deallocation of the memory at `address`.
+    ///
+    /// The `pool` must be the same as the one the allocation was recorded with by
+    /// [`Client::memory_alloc`]. `callstack_depth` has the same meaning as for that method.
+    pub fn memory_free(&self, pool: Option<PoolName>, address: usize, callstack_depth: u16) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            let ptr = address as *const std::ffi::c_void;
+            let depth = adjust_stack_depth(callstack_depth).into();
+            match (pool, depth) {
+                (None, 0) => sys::___tracy_emit_memory_free(ptr, 1),
+                (None, _) => sys::___tracy_emit_memory_free_callstack(ptr, depth, 1),
+                // SAFE: We made sure the pool name is null-terminated and lives forever.
+                (Some(pool), 0) => {
+                    let name = pool.0.as_ptr().cast();
+                    sys::___tracy_emit_memory_free_named(ptr, 1, name);
+                }
+                (Some(pool), _) => {
+                    let name = pool.0.as_ptr().cast();
+                    sys::___tracy_emit_memory_free_callstack_named(ptr, depth, 1, name);
+                }
+            }
+        }
+    }
+
+    /// Make the allocations on the current thread through the allocators constructed with
+    /// [`ProfiledAllocator::with_thread_pools`] report to `pool`, returning the previous pool.
+    ///
+    /// This attributes the memory to the logical phase of the program the thread is in. The
+    /// `tracing-tracy` crate does this automatically for the spans entered on the thread if its
+    /// `Config::span_memory_pools` is enabled.
+    pub fn set_thread_memory_pool(&self, pool: Option<PoolName>) -> Option<PoolName> {
+        THREAD_MEMORY_POOL
+            .try_with(|current| current.replace(pool))
+            .unwrap_or(None)
+    }
+
+    /// The pool set by [`Client::set_thread_memory_pool`] on the current thread.
+    #[must_use]
+    pub fn thread_memory_pool(&self) -> Option<PoolName> {
+        thread_memory_pool()
+    }
+}
+
+thread_local! {
+    /// The pool the allocators with thread pools report the allocations on this thread to.
+    static THREAD_MEMORY_POOL: Cell<Option<PoolName>> = const { Cell::new(None) };
+}
+
+fn thread_memory_pool() -> Option<PoolName> {
+    // The allocator may be used while the thread local storage is being torn down.
+    THREAD_MEMORY_POOL.try_with(Cell::get).unwrap_or(None)
+}
+
 /// A profiling wrapper around another allocator.
 ///
 /// See documentation for [`std::alloc`] for more information about global allocators.
@@ -210,7 +498,64 @@ macro_rules! set_thread_name {
 /// static GLOBAL: ProfiledAllocator<std::alloc::System> =
 ///     ProfiledAllocator::new(std::alloc::System, 100);
 /// ```
-pub struct ProfiledAllocator<T>(T, u16);
+pub struct ProfiledAllocator<T> {
+    inner: T,
+    callstack_depth: u16,
+    callstack_threshold: usize,
+    pool: Option<PoolName>,
+    /// Whether to report to the [`Client::set_thread_memory_pool`] instead of the `pool`.
+    thread_pools: bool,
+    heap_plot: Option<PlotName>,
+    /// The number of bytes currently allocated, maintained only for the `heap_plot`.
+    live_bytes: AtomicUsize,
+    /// Whether the `heap_plot` has been set up to show its values as bytes.
+    heap_plot_formatted: AtomicBool,
+}
+
+/// A name of a memory pool.
+///
+/// Create with the [`pool_name!`](crate::pool_name) macro.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PoolName(pub(crate) &'static str);
+
+impl PoolName {
+    /// Construct a `PoolName` dynamically, leaking the provided String.
+    ///
+    /// You should call this function once for a given name, and store the returned `PoolName` for
+    /// continued use, to avoid rapid memory use growth. Whenever possible, prefer the
+    /// [`pool_name!`](crate::pool_name) macro, which takes a literal name and doesn't leak memory.
+    ///
+    /// The resulting value may be used as an argument for the [`ProfiledAllocator::new_named`]
+    /// method.
+    #[must_use]
+    pub fn new_leak(name: String) -> Self {
+        #[cfg(feature = "enable")]
+        {
+            // Ensure the name is null-terminated.
+            let mut name = name;
+            name.push('\0');
+            // Drop excess capacity by converting into a boxed str, then leak.
+            let name = Box::leak(name.into_boxed_str());
+            Self(name)
+        }
+        #[cfg(not(feature = "enable"))]
+        {
+            drop(name);
+            Self("\0")
+        }
+    }
+}
+
+/// Construct a [`PoolName`].
+///
+/// The resulting value may be used as an argument for the [`ProfiledAllocator::new_named`]
+/// method. The macro can be used in a `const` context.
+#[macro_export]
+macro_rules! pool_name {
+    ($name: literal) => {{
+        unsafe { $crate::internal::create_pool_name(concat!($name, "\0")) }
+    }};
+}
 
 impl<T> ProfiledAllocator<T> {
     /// Construct a new `ProfiledAllocator`.
@@ -220,72 +565,515 @@ impl<T> ProfiledAllocator<T> {
     /// enabling callstack collection introduces a non-trivial amount of overhead to each
     /// allocation and deallocation.
     pub const fn new(inner_allocator: T, callstack_depth: u16) -> Self {
-        Self(inner_allocator, adjust_stack_depth(callstack_depth))
+        Self {
+            inner: inner_allocator,
+            callstack_depth: adjust_stack_depth(callstack_depth),
+            callstack_threshold: 0,
+            pool: None,
+            thread_pools: false,
+            heap_plot: None,
+            live_bytes: AtomicUsize::new(0),
+            heap_plot_formatted: AtomicBool::new(false),
+        }
     }
 
-    fn emit_alloc(&self, ptr: *mut u8, size: usize) {
-        #[cfg(feature = "enable")]
-        unsafe {
-            Client::start();
-            if self.1 == 0 {
-                let () = sys::___tracy_emit_memory_alloc(ptr.cast(), size, 1);
-            } else {
-                let () =
-                    sys::___tracy_emit_memory_alloc_callstack(ptr.cast(), size, self.1.into(), 1);
-            }
+    /// Construct a new `ProfiledAllocator` which reports its allocations to a named memory pool.
+    ///
+    /// The profiler presents each named pool separately from the allocations made through the
+    /// unnamed allocators, which is useful to tell apart e.g. different arenas. Allocations and
+    /// deallocations of the same memory must be made through allocators with the same pool name.
+    ///
+    /// `callstack_depth` has the same meaning as for [`ProfiledAllocator::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tracy_client::{pool_name, ProfiledAllocator};
+    /// static NETWORK_BUFFERS: ProfiledAllocator<std::alloc::System> =
+    ///     ProfiledAllocator::new_named(std::alloc::System, 0, pool_name!("network buffers"));
+    /// ```
+    pub const fn new_named(inner_allocator: T, callstack_depth: u16, pool_name: PoolName) -> Self {
+        Self {
+            inner: inner_allocator,
+            callstack_depth: adjust_stack_depth(callstack_depth),
+            callstack_threshold: 0,
+            pool: Some(pool_name),
+            thread_pools: false,
+            heap_plot: None,
+            live_bytes: AtomicUsize::new(0),
+            heap_plot_formatted: AtomicBool::new(false),
         }
     }
 
-    fn emit_free(&self, ptr: *mut u8) {
-        #[cfg(feature = "enable")]
+    /// Construct a new `ProfiledAllocator` which collects callstacks only for the allocations of
+    /// at least `callstack_threshold` bytes.
+    ///
+    /// Allocations smaller than the threshold, and their deallocations, are reported without a
+    /// callstack. This allows restricting the overhead of the callstack collection to the large
+    /// allocations, which are usually the interesting ones. `callstack_depth` has the same
+    /// meaning as for [`ProfiledAllocator::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracy_client::*;
+    /// #[global_allocator]
+    /// static GLOBAL: ProfiledAllocator<std::alloc::System> =
+    ///     ProfiledAllocator::with_callstack_threshold(std::alloc::System, 32, 64 * 1024);
+    /// ```
+    pub const fn with_callstack_threshold(
+        inner_allocator: T,
+        callstack_depth: u16,
+        callstack_threshold: usize,
+    ) -> Self {
+        Self {
+            inner: inner_allocator,
+            callstack_depth: adjust_stack_depth(callstack_depth),
+            callstack_threshold,
+            pool: None,
+            thread_pools: false,
+            heap_plot: None,
+            live_bytes: AtomicUsize::new(0),
+            heap_plot_formatted: AtomicBool::new(false),
+        }
+    }
+
+    /// Construct a new `ProfiledAllocator` which additionally plots the number of bytes currently
+    /// allocated through it.
+    ///
+    /// The plot is updated after every allocation and deallocation, which allows correlating the
+    /// heap size with the other plots. `callstack_depth` has the same meaning as for
+    /// [`ProfiledAllocator::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracy_client::*;
+    /// #[global_allocator]
+    /// static GLOBAL: ProfiledAllocator<std::alloc::System> =
+    ///     ProfiledAllocator::with_heap_plot(std::alloc::System, 0, plot_name!("heap size"));
+    /// ```
+    pub const fn with_heap_plot(
+        inner_allocator: T,
+        callstack_depth: u16,
+        plot_name: PlotName,
+    ) -> Self {
+        Self {
+            inner: inner_allocator,
+            callstack_depth: adjust_stack_depth(callstack_depth),
+            callstack_threshold: 0,
+            pool: None,
+            thread_pools: false,
+            heap_plot: Some(plot_name),
+            live_bytes: AtomicUsize::new(0),
+            heap_plot_formatted: AtomicBool::new(false),
+        }
+    }
+
+    /// Construct a new `ProfiledAllocator` which reports the allocations to the pool set for the
+    /// allocating thread with [`Client::set_thread_memory_pool`].
+    ///
+    /// As the memory must be freed in the pool it was allocated in, which may differ from the pool
+    /// of the thread freeing it, this allocator reserves space in front of each allocation of the
+    /// [`GlobalAlloc`](alloc::GlobalAlloc) implementation to remember its pool. The allocations
+    /// made through the `Allocator` implementation ignore the thread pool.
+    ///
+    /// `callstack_depth` has the same meaning as for [`ProfiledAllocator::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracy_client::*;
+    /// #[global_allocator]
+    /// static GLOBAL: ProfiledAllocator<std::alloc::System> =
+    ///     ProfiledAllocator::with_thread_pools(std::alloc::System, 0);
+    /// ```
+    pub const fn with_thread_pools(inner_allocator: T, callstack_depth: u16) -> Self {
+        Self {
+            inner: inner_allocator,
+            callstack_depth: adjust_stack_depth(callstack_depth),
+            callstack_threshold: 0,
+            pool: None,
+            thread_pools: true,
+            heap_plot: None,
+            live_bytes: AtomicUsize::new(0),
+            heap_plot_formatted: AtomicBool::new(false),
+        }
+    }
+
+    /// Update the number of the allocated bytes by `size` bytes and plot it.
+    fn plot_heap(&self, size: usize, allocated: bool) {
+        let Some(plot) = self.heap_plot else { return };
+        let live = if allocated {
+            self.live_bytes
+                .fetch_add(size, Ordering::Relaxed)
+                .wrapping_add(size)
+        } else {
+            self.live_bytes
+                .fetch_sub(size, Ordering::Relaxed)
+                .wrapping_sub(size)
+        };
+        let client = Client::start();
+        if !self.heap_plot_formatted.swap(true, Ordering::Relaxed) {
+            client.set_plot_format(plot, PlotFormat::Memory);
+        }
+        client.plot_int(plot, i64::try_from(live).unwrap_or(i64::MAX));
+    }
+
+    /// The callstack depth to report an allocation of `size` bytes with.
+    fn callstack_depth(&self, size: usize) -> u16 {
+        if size < self.callstack_threshold {
+            0
+        } else {
+            self.callstack_depth
+        }
+    }
+
+    fn emit_alloc(&self, pool: Option<PoolName>, ptr: *mut u8, size: usize) {
+        let depth = self.callstack_depth(size);
+        Client::start().memory_alloc(pool, ptr as usize, size, depth);
+        self.plot_heap(size, true);
+    }
+
+    fn emit_free(&self, pool: Option<PoolName>, ptr: *mut u8, size: usize) {
+        let depth = self.callstack_depth(size);
+        // The client was started by the allocation of this memory.
+        Client(()).memory_free(pool, ptr as usize, depth);
+        self.plot_heap(size, false);
+    }
+}
+
+/// The layout of an allocation of an allocator with thread pools, with the pool stored right in
+/// front of the memory of `layout`, and the offset of that memory.
+fn pool_header_layout(layout: alloc::Layout) -> Option<(alloc::Layout, usize)> {
+    alloc::Layout::new::<Option<PoolName>>().extend(layout).ok()
+}
+
+impl<T: alloc::GlobalAlloc> ProfiledAllocator<T> {
+    /// Allocate the memory for `layout` in the pool of the current thread.
+    ///
+    /// `allocate` must return either null or the memory for the layout it is passed.
+    fn alloc_in_thread_pool(
+        &self,
+        layout: alloc::Layout,
+        allocate: impl FnOnce(alloc::Layout) -> *mut u8,
+    ) -> *mut u8 {
+        let Some((outer, offset)) = pool_header_layout(layout) else {
+            return std::ptr::null_mut();
+        };
+        let base = allocate(outer);
+        if base.is_null() {
+            return base;
+        }
+        let pool = thread_memory_pool();
+        let alloc = unsafe {
+            // SAFE: `offset` is within the allocation, and the header right in front of it is
+            // aligned for the pool, as the header comes first in `outer`.
+            let alloc = base.add(offset);
+            alloc.cast::<Option<PoolName>>().sub(1).write(pool);
+            alloc
+        };
+        self.emit_alloc(pool, alloc, layout.size());
+        alloc
+    }
+
+    /// The pool of the allocation at `ptr`, and the start and layout of the underlying allocation.
+    unsafe fn thread_pool_allocation(
+        ptr: *mut u8,
+        layout: alloc::Layout,
+    ) -> (Option<PoolName>, *mut u8, alloc::Layout) {
+        // The header layout was computed for this very layout when the memory was allocated.
+        let (outer, offset) = pool_header_layout(layout).expect("valid header layout");
         unsafe {
-            if self.1 == 0 {
-                let () = sys::___tracy_emit_memory_free(ptr.cast(), 1);
-            } else {
-                let () = sys::___tracy_emit_memory_free_callstack(ptr.cast(), self.1.into(), 1);
-            }
+            // SAFE: the caller guarantees that `ptr` was allocated by `alloc_in_thread_pool`.
+            let pool = ptr.cast::<Option<PoolName>>().sub(1).read();
+            (pool, ptr.sub(offset), outer)
         }
     }
 }
 
 unsafe impl<T: alloc::GlobalAlloc> alloc::GlobalAlloc for ProfiledAllocator<T> {
     unsafe fn alloc(&self, layout: alloc::Layout) -> *mut u8 {
+        if self.thread_pools {
+            return self.alloc_in_thread_pool(layout, |outer| unsafe {
+                // SAFE: `outer` is larger than the non-zero sized `layout`.
+                self.inner.alloc(outer)
+            });
+        }
         let alloc = unsafe {
             // SAFE: all invariants satisfied by the caller.
-            self.0.alloc(layout)
+            self.inner.alloc(layout)
         };
-        self.emit_alloc(alloc, layout.size());
+        self.emit_alloc(self.pool, alloc, layout.size());
         alloc
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: alloc::Layout) {
-        self.emit_free(ptr);
+        if self.thread_pools {
+            unsafe {
+                // SAFE: all invariants satisfied by the caller.
+                let (pool, base, outer) = Self::thread_pool_allocation(ptr, layout);
+                self.emit_free(pool, ptr, layout.size());
+                self.inner.dealloc(base, outer);
+            }
+            return;
+        }
+        self.emit_free(self.pool, ptr, layout.size());
         unsafe {
             // SAFE: all invariants satisfied by the caller.
-            self.0.dealloc(ptr, layout);
+            self.inner.dealloc(ptr, layout);
         }
     }
 
     unsafe fn alloc_zeroed(&self, layout: alloc::Layout) -> *mut u8 {
+        if self.thread_pools {
+            return self.alloc_in_thread_pool(layout, |outer| unsafe {
+                // SAFE: `outer` is larger than the non-zero sized `layout`.
+                self.inner.alloc_zeroed(outer)
+            });
+        }
         let alloc = unsafe {
             // SAFE: all invariants satisfied by the caller.
-            self.0.alloc_zeroed(layout)
+            self.inner.alloc_zeroed(layout)
         };
-        self.emit_alloc(alloc, layout.size());
+        self.emit_alloc(self.pool, alloc, layout.size());
         alloc
     }
 
     unsafe fn realloc(&self, ptr: *mut u8, layout: alloc::Layout, new_size: usize) -> *mut u8 {
-        self.emit_free(ptr);
+        if self.thread_pools {
+            // The memory stays in the pool it was originally allocated in.
+            let Ok(new_layout) = alloc::Layout::from_size_align(new_size, layout.align()) else {
+                return std::ptr::null_mut();
+            };
+            let Some((new_outer, offset)) = pool_header_layout(new_layout) else {
+                return std::ptr::null_mut();
+            };
+            return unsafe {
+                // SAFE: all invariants satisfied by the caller.
+                let (pool, base, outer) = Self::thread_pool_allocation(ptr, layout);
+                self.emit_free(pool, ptr, layout.size());
+                let new_base = self.inner.realloc(base, outer, new_outer.size());
+                if new_base.is_null() {
+                    // The old memory remains allocated if the reallocation fails.
+                    self.emit_alloc(pool, ptr, layout.size());
+                    return new_base;
+                }
+                let alloc = new_base.add(offset);
+                self.emit_alloc(pool, alloc, new_size);
+                alloc
+            };
+        }
+        self.emit_free(self.pool, ptr, layout.size());
         let alloc = unsafe {
             // SAFE: all invariants satisfied by the caller.
-            self.0.realloc(ptr, layout, new_size)
+            self.inner.realloc(ptr, layout, new_size)
         };
-        self.emit_alloc(alloc, new_size);
+        self.emit_alloc(self.pool, alloc, new_size);
         alloc
     }
 }
 
+/// Profile the allocations of individual collections, for example:
+///
+/// ```ignore
+/// #![feature(allocator_api)]
+/// let mut scratch = Vec::new_in(tracy_client::ProfiledAllocator::new(std::alloc::Global, 0));
+/// scratch.push(42);
+/// ```
+#[cfg(feature = "allocator-api")]
+unsafe impl<T: alloc::Allocator> alloc::Allocator for ProfiledAllocator<T> {
+    fn allocate(&self, layout: alloc::Layout) -> Result<NonNull<[u8]>, alloc::AllocError> {
+        let alloc = self.inner.allocate(layout)?;
+        self.emit_alloc(self.pool, alloc.cast().as_ptr(), layout.size());
+        Ok(alloc)
+    }
+
+    fn allocate_zeroed(&self, layout: alloc::Layout) -> Result<NonNull<[u8]>, alloc::AllocError> {
+        let alloc = self.inner.allocate_zeroed(layout)?;
+        self.emit_alloc(self.pool, alloc.cast().as_ptr(), layout.size());
+        Ok(alloc)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: alloc::Layout) {
+        self.emit_free(self.pool, ptr.as_ptr(), layout.size());
+        unsafe {
+            // SAFE: all invariants satisfied by the caller.
+            self.inner.deallocate(ptr, layout);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: alloc::Layout,
+        new_layout: alloc::Layout,
+    ) -> Result<NonNull<[u8]>, alloc::AllocError> {
+        self.emit_realloc(ptr, old_layout, new_layout, || unsafe {
+            // SAFE: all invariants satisfied by the caller.
+            self.inner.grow(ptr, old_layout, new_layout)
+        })
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: alloc::Layout,
+        new_layout: alloc::Layout,
+    ) -> Result<NonNull<[u8]>, alloc::AllocError> {
+        self.emit_realloc(ptr, old_layout, new_layout, || unsafe {
+            // SAFE: all invariants satisfied by the caller.
+            self.inner.grow_zeroed(ptr, old_layout, new_layout)
+        })
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: alloc::Layout,
+        new_layout: alloc::Layout,
+    ) -> Result<NonNull<[u8]>, alloc::AllocError> {
+        self.emit_realloc(ptr, old_layout, new_layout, || unsafe {
+            // SAFE: all invariants satisfied by the caller.
+            self.inner.shrink(ptr, old_layout, new_layout)
+        })
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl<T> ProfiledAllocator<T> {
+    fn emit_realloc(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: alloc::Layout,
+        new_layout: alloc::Layout,
+        realloc: impl FnOnce() -> Result<NonNull<[u8]>, alloc::AllocError>,
+    ) -> Result<NonNull<[u8]>, alloc::AllocError> {
+        // The old memory must be reported as freed before it can be reused by another thread.
+        self.emit_free(self.pool, ptr.as_ptr(), old_layout.size());
+        match realloc() {
+            Ok(alloc) => {
+                self.emit_alloc(self.pool, alloc.cast().as_ptr(), new_layout.size());
+                Ok(alloc)
+            }
+            Err(error) => {
+                // The old memory remains allocated if the reallocation fails.
+                self.emit_alloc(self.pool, ptr.as_ptr(), old_layout.size());
+                Err(error)
+            }
+        }
+    }
+}
+
+/// A comma-separated list of the features of this crate the Tracy client has been built with.
+///
+/// This is useful to make traces self-documenting, e.g. by including it in the
+/// [`Client::app_info`], which reveals whether sampling was even enabled when looking at a trace
+/// later. Returns `"none"` if no features are enabled.
+///
+/// # Example
+///
+/// ```rust
+/// let client = tracy_client::Client::start();
+/// client.app_info(&format!("tracy-client features: {}", tracy_client::feature_summary()));
+/// ```
+#[must_use]
+pub fn feature_summary() -> &'static str {
+    const FEATURES: [(&str, bool); 16] = [
+        ("enable", cfg!(feature = "enable")),
+        ("broadcast", cfg!(feature = "broadcast")),
+        ("callstack-inlines", cfg!(feature = "callstack-inlines")),
+        ("code-transfer", cfg!(feature = "code-transfer")),
+        (
+            "context-switch-tracing",
+            cfg!(feature = "context-switch-tracing"),
+        ),
+        ("delayed-init", cfg!(feature = "delayed-init")),
+        ("fibers", cfg!(feature = "fibers")),
+        ("flush-on-exit", cfg!(feature = "flush-on-exit")),
+        ("manual-lifetime", cfg!(feature = "manual-lifetime")),
+        ("ondemand", cfg!(feature = "ondemand")),
+        ("only-ipv4", cfg!(feature = "only-ipv4")),
+        ("only-localhost", cfg!(feature = "only-localhost")),
+        ("sampling", cfg!(feature = "sampling")),
+        ("system-tracing", cfg!(feature = "system-tracing")),
+        ("timer-fallback", cfg!(feature = "timer-fallback")),
+        ("allocator-api", cfg!(feature = "allocator-api")),
+    ];
+    static SUMMARY: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    SUMMARY.get_or_init(|| {
+        let enabled = FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| *feature)
+            .collect::<Vec<_>>();
+        if enabled.is_empty() {
+            String::from("none")
+        } else {
+            enabled.join(", ")
+        }
+    })
+}
+
+/// The kinds of additional data the Tracy client collects, see [`active_collection_modes`].
+///
+/// Each of these reveals more about the system than the instrumentation does, which may have
+/// privacy implications worth pointing out to the users of an application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CollectionModes {
+    /// System level details, such as the kernel events, are collected.
+    pub system_tracing: bool,
+    /// The context switch data is collected.
+    pub context_switch: bool,
+    /// Callstacks are sampled periodically.
+    pub sampling: bool,
+    /// The machine code of the program can be transferred to the profiler.
+    pub code_transfer: bool,
+    /// The presence of the client is announced on the local network.
+    pub broadcast: bool,
+}
+
+/// The kinds of additional data collected by the Tracy client, according to the features this
+/// crate has been built with.
+///
+/// Without the `enable` feature nothing is collected at all, so every mode is inactive. Note that
+/// Tracy may still be unable to collect some data at runtime, e.g. for the lack of privileges.
+///
+/// # Example
+///
+/// ```rust
+/// let modes = tracy_client::active_collection_modes();
+/// if modes.sampling || modes.context_switch {
+///     eprintln!("warning: the profiler collects data about the whole system");
+/// }
+/// ```
+#[must_use]
+pub const fn active_collection_modes() -> CollectionModes {
+    let enabled = cfg!(feature = "enable");
+    CollectionModes {
+        system_tracing: enabled && cfg!(feature = "system-tracing"),
+        context_switch: enabled && cfg!(feature = "context-switch-tracing"),
+        sampling: enabled && cfg!(feature = "sampling"),
+        code_transfer: enabled && cfg!(feature = "code-transfer"),
+        broadcast: enabled && cfg!(feature = "broadcast"),
+    }
+}
+
+/// The version of the protocol the Tracy client built into this crate speaks with the profiler.
+///
+/// This is read from the Tracy sources at build time, so it follows `TRACY_CLIENT_SYS_SOURCE` when
+/// the client is built from another source tree.
+///
+/// A profiler can only connect to clients speaking its own protocol version, so this is useful to
+/// diagnose a profiler refusing the connection, e.g. when linking an external Tracy library via
+/// `TRACY_CLIENT_LIB`, which must be of the same Tracy release as the bindings of
+/// `tracy-client-sys`.
+#[must_use]
+pub fn protocol_version() -> u32 {
+    sys::PROTOCOL_VERSION
+}
+
 /// Clamp the stack depth to the maximum supported by Tracy.
 pub(crate) const fn adjust_stack_depth(depth: u16) -> u16 {
     #[cfg(windows)]
@@ -297,3 +1085,131 @@ pub(crate) const fn adjust_stack_depth(depth: u16) -> u16 {
         depth
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+
+    #[test]
+    fn message_callstack_routing() {
+        assert_eq!(MessageCallstack::new(0), MessageCallstack::None);
+        assert_eq!(MessageCallstack::new(1), MessageCallstack::Frames(1));
+        assert_eq!(MessageCallstack::new(16), MessageCallstack::Frames(16));
+        assert_eq!(
+            MessageCallstack::new(u16::MAX),
+            MessageCallstack::Frames(adjust_stack_depth(u16::MAX))
+        );
+    }
+
+    #[test]
+    fn feature_summary_lists_enabled_features() {
+        let features = feature_summary().split(", ").collect::<Vec<_>>();
+        assert_eq!(features.contains(&"enable"), cfg!(feature = "enable"));
+        assert_eq!(features.contains(&"sampling"), cfg!(feature = "sampling"));
+        assert_eq!(features.contains(&"fibers"), cfg!(feature = "fibers"));
+        assert!(!features.is_empty());
+    }
+
+    #[test]
+    fn collection_modes_follow_features() {
+        let modes = active_collection_modes();
+        #[cfg(all(
+            feature = "enable",
+            feature = "system-tracing",
+            feature = "context-switch-tracing",
+            feature = "sampling",
+            feature = "code-transfer",
+            feature = "broadcast",
+        ))]
+        assert_eq!(
+            modes,
+            CollectionModes {
+                system_tracing: true,
+                context_switch: true,
+                sampling: true,
+                code_transfer: true,
+                broadcast: true,
+            },
+        );
+        #[cfg(not(feature = "enable"))]
+        assert!(
+            !(modes.system_tracing
+                || modes.context_switch
+                || modes.sampling
+                || modes.code_transfer
+                || modes.broadcast)
+        );
+        assert_eq!(
+            modes.sampling,
+            cfg!(all(feature = "enable", feature = "sampling"))
+        );
+    }
+
+    #[test]
+    fn protocol_version_matches_bindings() {
+        assert_eq!(protocol_version(), sys::PROTOCOL_VERSION);
+        assert_ne!(protocol_version(), 0);
+        assert!(sys::TRACY_VERSION >= (0, 10, 0));
+    }
+
+    #[test]
+    fn thread_name_with_null() {
+        assert_eq!(truncated_c_string("worker\0tail").as_bytes(), b"worker");
+        assert_eq!(truncated_c_string("\0").as_bytes(), b"");
+        assert_eq!(truncated_c_string("worker").as_bytes(), b"worker");
+        let client = Client::start();
+        client.set_thread_name("worker\0tail");
+        client.set_thread_name_with_hint("\0", 0);
+    }
+
+    #[test]
+    fn heap_plot_tracks_live_bytes() {
+        let allocator = ProfiledAllocator::with_heap_plot(System, 0, plot_name!("test heap"));
+        let live = || allocator.live_bytes.load(Ordering::Relaxed);
+        let small = Layout::from_size_align(64, 8).unwrap();
+        let large = Layout::from_size_align(1024, 8).unwrap();
+        unsafe {
+            let a = allocator.alloc(small);
+            let b = allocator.alloc_zeroed(small);
+            assert_eq!(live(), 128);
+            let b = allocator.realloc(b, small, 1024);
+            assert_eq!(live(), 64 + 1024);
+            allocator.dealloc(a, small);
+            allocator.dealloc(b, large);
+        }
+        assert_eq!(live(), 0);
+    }
+
+    #[test]
+    fn thread_pools_remember_the_allocating_pool() {
+        let _client = Client::start();
+        let allocator = ProfiledAllocator::with_thread_pools(System, 0);
+        let pool_of = |ptr, layout| unsafe {
+            ProfiledAllocator::<System>::thread_pool_allocation(ptr, layout).0
+        };
+        let phase = pool_name!("phase");
+        for align in [1, 8, 16, 64, 4096] {
+            let layout = Layout::from_size_align(24, align).unwrap();
+            let previous = Client(()).set_thread_memory_pool(Some(phase));
+            unsafe {
+                let a = allocator.alloc(layout);
+                assert_eq!(a as usize % align, 0);
+                a.write_bytes(0xAB, layout.size());
+                Client(()).set_thread_memory_pool(previous);
+                let b = allocator.alloc_zeroed(layout);
+                assert!(pool_of(a, layout) == Some(phase));
+                assert!(pool_of(b, layout).is_none());
+                // Reallocations stay in the original pool, irrespective of the current one.
+                let a = allocator.realloc(a, layout, 4096);
+                let grown = Layout::from_size_align(4096, align).unwrap();
+                assert_eq!(a as usize % align, 0);
+                assert!(pool_of(a, grown) == Some(phase));
+                assert_eq!(*a.add(layout.size() - 1), 0xAB);
+                allocator.dealloc(a, grown);
+                allocator.dealloc(b, layout);
+            }
+        }
+        assert!(Client(()).thread_memory_pool().is_none());
+    }
+}
@@ -1,4 +1,6 @@
 use crate::Client;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
 
 /// Name of a plot.
 ///
@@ -34,6 +36,21 @@ impl PlotName {
     }
 }
 
+/// The format in which the profiler presents the values of a plot.
+///
+/// Set with [`Client::set_plot_format`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlotFormat {
+    /// Plain numbers.
+    Number,
+    /// Numbers of bytes, shown with units such as KB or MB.
+    Memory,
+    /// Percentages.
+    Percentage,
+    /// Watts.
+    Watt,
+}
+
 /// Instrumentation for drawing 2D plots.
 impl Client {
     /// Add a point with an y-axis value of `value` to the plot named `plot_name`.
@@ -53,6 +70,69 @@ impl Client {
             let () = sys::___tracy_emit_plot(plot_name.0.as_ptr().cast(), value);
         }
     }
+
+    /// Add a point with an integer y-axis value of `value` to the plot named `plot_name`.
+    ///
+    /// Unlike [`Client::plot`], this represents all of the `i64` values exactly, which makes it
+    /// the better choice for counters such as a number of bytes.
+    pub fn plot_int(&self, plot_name: PlotName, value: i64) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            // SAFE: We made sure the `plot` refers to a null-terminated string.
+            let () = sys::___tracy_emit_plot_int(plot_name.0.as_ptr().cast(), value);
+        }
+    }
+
+    /// Set the format in which the profiler presents the values of the plot named `plot_name`.
+    ///
+    /// The plot is drawn as a filled area in the default color of the profiler.
+    pub fn set_plot_format(&self, plot_name: PlotName, format: PlotFormat) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            let format = match format {
+                PlotFormat::Number => sys::TracyPlotFormatEnum_TracyPlotFormatNumber,
+                PlotFormat::Memory => sys::TracyPlotFormatEnum_TracyPlotFormatMemory,
+                PlotFormat::Percentage => sys::TracyPlotFormatEnum_TracyPlotFormatPercentage,
+                PlotFormat::Watt => sys::TracyPlotFormatEnum_TracyPlotFormatWatt,
+            };
+            // SAFE: We made sure the `plot` refers to a null-terminated string.
+            let () = sys::___tracy_emit_plot_config(
+                plot_name.0.as_ptr().cast(),
+                format as std::os::raw::c_int,
+                0,
+                1,
+                0,
+            );
+        }
+    }
+
+    /// Add a point to the plot named `plot_name`, unless `value` is the same as the previous one.
+    ///
+    /// This is useful for values that rarely change, such as a number of open connections, which
+    /// would otherwise add a point to the trace every time they are sampled. Note that Tracy
+    /// draws lines between the points of a plot, so a value that stays the same for a while
+    /// before changing looks as if it had been changing gradually over that whole period.
+    ///
+    /// The previous value is remembered per [`PlotName`] in a global map, updated only by this
+    /// method. Points added with [`Client::plot`] are not taken into account.
+    pub fn plot_if_changed(&self, plot_name: PlotName, value: f64) {
+        #[cfg(feature = "enable")]
+        if value_changed(plot_name, value) {
+            self.plot(plot_name, value);
+        }
+    }
+}
+
+/// The last values added with [`Client::plot_if_changed`], keyed by the address of the plot name.
+#[cfg_attr(not(feature = "enable"), allow(dead_code))]
+static LAST_VALUES: Mutex<BTreeMap<usize, u64>> = Mutex::new(BTreeMap::new());
+
+/// Remember `value` as the last value of the plot, returning whether it differs from the previous.
+#[cfg_attr(not(feature = "enable"), allow(dead_code))]
+fn value_changed(plot_name: PlotName, value: f64) -> bool {
+    let mut values = LAST_VALUES.lock().unwrap_or_else(PoisonError::into_inner);
+    // Compare the bits, so that a NaN is considered unchanged by another identical NaN.
+    values.insert(plot_name.0.as_ptr() as usize, value.to_bits()) != Some(value.to_bits())
 }
 
 /// Construct a [`PlotName`].
@@ -79,3 +159,19 @@ macro_rules! plot {
             .plot($crate::plot_name!($name), $value)
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_values_are_coalesced() {
+        let plot = PlotName::new_leak(String::from("coalesced"));
+        assert!(value_changed(plot, 1.0));
+        assert!(!value_changed(plot, 1.0));
+        assert!(value_changed(plot, 2.0));
+        assert!(value_changed(plot, 1.0));
+        assert!(value_changed(plot, f64::NAN));
+        assert!(!value_changed(plot, f64::NAN));
+    }
+}
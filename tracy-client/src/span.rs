@@ -1,5 +1,8 @@
 use crate::{adjust_stack_depth, Client};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::ffi::CString;
+use std::sync::{PoisonError, RwLock};
 
 /// A handle representing a span of execution.
 ///
@@ -17,7 +20,8 @@ pub struct Span {
 
 /// A statically allocated location information for a span.
 ///
-/// Construct with the [`span_location!`](crate::span_location) macro.
+/// Construct with the [`span_location!`](crate::span_location) macro or, for locations only known
+/// at runtime, with [`SpanLocation::new_leak`].
 pub struct SpanLocation {
     #[cfg(feature = "enable")]
     pub(crate) _function_name: CString,
@@ -30,6 +34,130 @@ pub struct SpanLocation {
 unsafe impl Send for SpanLocation {}
 unsafe impl Sync for SpanLocation {}
 
+impl SpanLocation {
+    /// Construct a `SpanLocation` dynamically, leaking the provided strings.
+    ///
+    /// You should call this function once for a given location, and store the returned reference
+    /// for continued use, to avoid rapid memory use growth. Whenever possible, prefer the
+    /// [`span_location!`](crate::span_location) macro, which doesn't leak memory.
+    ///
+    /// The strings will be cut short at the first null byte, if any.
+    ///
+    /// The resulting value may be used as an argument for the [`Client::span`] method.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let location = tracy_client::SpanLocation::new_leak(
+    ///     Some(String::from("handle request")),
+    ///     String::from("server::handle"),
+    ///     String::from(file!()),
+    ///     line!(),
+    /// );
+    /// let _span = tracy_client::Client::start().span(location, 0);
+    /// ```
+    #[must_use]
+    pub fn new_leak(
+        name: Option<String>,
+        function: String,
+        file: String,
+        line: u32,
+    ) -> &'static Self {
+        #[cfg(feature = "enable")]
+        {
+            fn leak(mut string: String) -> *const std::os::raw::c_char {
+                // Ensure the string is null-terminated.
+                string.push('\0');
+                // Drop excess capacity by converting into a boxed str, then leak.
+                Box::leak(string.into_boxed_str()).as_ptr().cast()
+            }
+            Box::leak(Box::new(Self {
+                data: sys::___tracy_source_location_data {
+                    name: name.map_or(std::ptr::null(), leak),
+                    function: leak(function),
+                    file: leak(file),
+                    line,
+                    color: 0,
+                },
+                _function_name: CString::default(),
+            }))
+        }
+        #[cfg(not(feature = "enable"))]
+        {
+            drop((name, function, file));
+            Box::leak(Box::new(Self { _internal: () }))
+        }
+    }
+}
+
+/// A cache of [`SpanLocation`]s for span names only known at runtime.
+///
+/// Constructing a [`SpanLocation::new_leak`] for every span leaks memory rapidly. This cache
+/// instead constructs a location once for each distinct name and returns the same location for
+/// all the later uses of the name, which suits e.g. dispatchers naming their spans after the
+/// handled message type. All the locations share the function, file and line given upon the
+/// construction of the cache.
+///
+/// Like [`SpanLocation::new_leak`], the cached locations are never freed and live for the
+/// remainder of the program, even if the cache itself is dropped. The cache should thus only be
+/// used with a bounded set of names.
+///
+/// # Example
+///
+/// ```rust
+/// use tracy_client::SpanLocationCache;
+/// static LOCATIONS: SpanLocationCache =
+///     SpanLocationCache::new("dispatcher::dispatch", file!(), line!());
+///
+/// # let message_type = "ping";
+/// let client = tracy_client::Client::start();
+/// let _span = client.span(LOCATIONS.location(message_type), 0);
+/// ```
+pub struct SpanLocationCache {
+    function: &'static str,
+    file: &'static str,
+    line: u32,
+    locations: RwLock<BTreeMap<Box<str>, &'static SpanLocation>>,
+}
+
+impl SpanLocationCache {
+    /// Construct an empty cache of locations with the given function, file and line.
+    #[must_use]
+    pub const fn new(function: &'static str, file: &'static str, line: u32) -> Self {
+        Self {
+            function,
+            file,
+            line,
+            locations: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Obtain the location of the spans called `name`, constructing it if necessary.
+    pub fn location(&self, name: &str) -> &'static SpanLocation {
+        let location = self
+            .locations
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .copied();
+        location.unwrap_or_else(|| {
+            *self
+                .locations
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(name.into())
+                .or_insert_with(|| {
+                    SpanLocation::new_leak(
+                        Some(name.to_owned()),
+                        self.function.to_owned(),
+                        self.file.to_owned(),
+                        self.line,
+                    )
+                })
+        })
+    }
+}
+
 /// Instrumentation for timed regions, spans or zones of execution.
 impl Client {
     /// Start a new Tracy span/zone.
@@ -86,6 +214,9 @@ impl Client {
     /// profiler. Prefer the [`Client::span`] as a allocation-free and faster alternative when
     /// possible.
     ///
+    /// The `name`, `function` and `file` are copied before this function returns, so they may be
+    /// computed at runtime and need not outlive the returned `Span`.
+    ///
     /// Specifying a non-zero `callstack_depth` will enable collection of callstack for this
     /// message. The number provided will limit the number of call frames collected. Note that
     /// enabling callstack collection introduces a non-trivial amount of overhead to this call. On
@@ -144,8 +275,15 @@ impl Client {
     }
 }
 
+/// Instrumentation for annotating a span with data known only at runtime.
+///
+/// The annotations apply to the zone represented by this `Span`, which is open from the moment
+/// the `Span` is created until it is dropped. As thus these methods always get called in between
+/// the zone's beginning and end.
 impl Span {
     /// Emit a numeric value associated with this span.
+    ///
+    /// This may be called multiple times, in which case every value is recorded.
     pub fn emit_value(&self, value: u64) {
         #[cfg(feature = "enable")]
         unsafe {
@@ -155,25 +293,143 @@ impl Span {
     }
 
     /// Emit some text associated with this span.
+    ///
+    /// This may be called multiple times, in which case every piece of text is recorded. Tracy
+    /// limits the text to less than 64KiB; longer text is truncated at a character boundary.
     pub fn emit_text(&self, text: &str) {
         #[cfg(feature = "enable")]
         unsafe {
+            let text = truncate_to_length(text, MAX_TEXT_LENGTH);
             // SAFE: the only way to construct `Span` is by creating a valid tracy zone context.
             let () = sys::___tracy_emit_zone_text(self.zone, text.as_ptr().cast(), text.len());
         }
     }
 
+    /// Emit the [`Debug`](std::fmt::Debug) representation of `value` as text associated with this
+    /// span.
+    ///
+    /// This is equivalent to `span.emit_text(&format!("{value:?}"))`, except that the text is
+    /// formatted into a buffer reused by all calls on the same thread, rather than a newly allocated
+    /// string every time.
+    pub fn emit_debug(&self, value: &dyn std::fmt::Debug) {
+        #[cfg(feature = "enable")]
+        with_debug_text(value, |text| self.emit_text(text));
+    }
+
     /// Emit a color associated with this span.
-    pub fn emit_color(&self, color: u32) {
+    ///
+    /// The colour may be provided as a [`Color`](crate::Color), or as an RGBA `u32`, where the least
+    /// significant 8 bits represent the alpha component and most significant 8 bits represent the
+    /// red component.
+    pub fn emit_color(&self, color: impl Into<crate::Color>) {
         #[cfg(feature = "enable")]
         unsafe {
             // SAFE: the only way to construct `Span` is by creating a valid tracy zone context.
-            // TODO: verify if we need to shift by 8 or not...?
-            let () = sys::___tracy_emit_zone_color(self.zone, color);
+            let () = sys::___tracy_emit_zone_color(self.zone, color.into().to_tracy_u32());
+        }
+    }
+
+    /// Override the name of this span.
+    ///
+    /// This is useful when a meaningful name for the span only becomes known after the span has
+    /// already been started. Tracy limits the name to less than 64KiB; longer names are truncated
+    /// at a character boundary.
+    pub fn emit_name(&self, name: &str) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            let name = truncate_to_length(name, MAX_TEXT_LENGTH);
+            // SAFE: the only way to construct `Span` is by creating a valid tracy zone context.
+            let () = sys::___tracy_emit_zone_name(self.zone, name.as_ptr().cast(), name.len());
+        }
+    }
+
+    /// Convert this span into an opaque handle without ending its zone.
+    ///
+    /// This transfers the ownership of the zone to the handle, which is useful when the zone must
+    /// be begun and ended on different sides of an FFI boundary that can only carry an integer or
+    /// a pointer-sized value. The zone is ended once the handle is turned back into a `Span` with
+    /// [`Span::from_raw`] and that is dropped. Discarding the handle leaves the zone open forever.
+    ///
+    /// The value of the handle carries no meaning of its own.
+    #[must_use = "the zone is only ended once the handle is turned back into a `Span`"]
+    pub fn into_raw(self) -> u64 {
+        #[cfg(feature = "enable")]
+        {
+            let this = std::mem::ManuallyDrop::new(self);
+            u64::from(this.zone.id) << 32 | u64::from(this.zone.active != 0)
+        }
+        #[cfg(not(feature = "enable"))]
+        0
+    }
+
+    /// Take back the ownership of a zone from a handle returned by [`Span::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `raw` must have been returned by [`Span::into_raw`] on the current thread, and must not
+    /// have been passed to this function already. Converting the same handle back more than once
+    /// would end the zone more than once, corrupting the Tracy zone stack of the thread.
+    ///
+    /// Just like any other `Span`, the returned value must be dropped in the reverse order of the
+    /// spans being begun on this thread.
+    pub unsafe fn from_raw(raw: u64) -> Span {
+        #[cfg(feature = "enable")]
+        {
+            Span {
+                client: Client(()),
+                zone: sys::___tracy_c_zone_context {
+                    id: (raw >> 32) as u32,
+                    active: (raw & 1) as _,
+                },
+                _no_send_sync: std::marker::PhantomData,
+            }
+        }
+        #[cfg(not(feature = "enable"))]
+        Span {
+            _no_send_sync: std::marker::PhantomData,
         }
     }
 }
 
+/// Tracy asserts that zone text and names are strictly shorter than `u16::MAX` bytes.
+const MAX_TEXT_LENGTH: usize = u16::MAX as usize - 1;
+
+/// Format `value` into a thread-local buffer and call `f` with the result.
+///
+/// A fresh buffer is used if the `Debug` implementation of `value` itself ends up in here.
+#[cfg_attr(not(feature = "enable"), allow(dead_code))]
+fn with_debug_text<R>(value: &dyn std::fmt::Debug, f: impl FnOnce(&str) -> R) -> R {
+    use std::fmt::Write;
+    thread_local! {
+        static BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+    BUFFER.with(|buffer| {
+        let mut fresh = String::new();
+        let mut reused = buffer.try_borrow_mut();
+        let buffer = match reused {
+            Ok(ref mut buffer) => &mut **buffer,
+            Err(_) => &mut fresh,
+        };
+        buffer.clear();
+        let _ = write!(buffer, "{value:?}");
+        let result = f(buffer);
+        // Don't hold onto the memory of an occasional huge value for the lifetime of the thread.
+        buffer.shrink_to(MAX_TEXT_LENGTH);
+        result
+    })
+}
+
+/// Truncate `data` to at most `max_len` bytes without splitting a character.
+fn truncate_to_length(data: &str, mut max_len: usize) -> &str {
+    if data.len() <= max_len {
+        return data;
+    }
+    while !data.is_char_boundary(max_len) {
+        max_len -= 1;
+    }
+    &data[..max_len]
+}
+
 impl Drop for Span {
     fn drop(&mut self) {
         #[cfg(feature = "enable")]
@@ -194,10 +450,17 @@ impl Drop for Span {
 ///
 /// The resulting value may be used as an argument for the [`Client::span`] method.
 ///
+/// The default color of the zones with this location may be specified as the second argument,
+/// with the same convention as for [`Span::emit_color`]. This is cheaper than setting the color
+/// of each zone individually. A color with zero red, green and blue components leaves the color
+/// of the zones up to the profiler, which is also the default.
+///
 /// # Example
 ///
 /// ```rust
 /// let location: &'static tracy_client::SpanLocation = tracy_client::span_location!("some name");
+/// let red: &'static tracy_client::SpanLocation = tracy_client::span_location!("red", 0xFF0000FF);
+/// let blue = tracy_client::span_location!("blue", tracy_client::Color::BLUE);
 /// ```
 #[macro_export]
 macro_rules! span_location {
@@ -211,6 +474,7 @@ macro_rules! span_location {
                     $crate::internal::null(),
                     concat!(file!(), "\0").as_ptr(),
                     line!(),
+                    $crate::Color::DEFAULT,
                 )
             });
         &*LOC
@@ -225,6 +489,22 @@ macro_rules! span_location {
                     concat!($name, "\0").as_ptr(),
                     concat!(file!(), "\0").as_ptr(),
                     line!(),
+                    $crate::Color::DEFAULT,
+                )
+            });
+        &*LOC
+    }};
+    ($name: expr, $rgba: expr) => {{
+        struct S;
+        // String processing in `const` when, Oli?
+        static LOC: $crate::internal::Lazy<$crate::internal::SpanLocation> =
+            $crate::internal::Lazy::new(|| {
+                $crate::internal::make_span_location(
+                    $crate::internal::type_name::<S>(),
+                    concat!($name, "\0").as_ptr(),
+                    concat!(file!(), "\0").as_ptr(),
+                    line!(),
+                    $crate::Color::from($rgba),
                 )
             });
         &*LOC
@@ -258,6 +538,14 @@ macro_rules! span_location {
 ///
 /// Note, however, that collecting callstack introduces a non-trivial overhead at the point of
 /// instrumentation.
+///
+/// Finally, the default color of the span may be specified, as with [`span_location!`]:
+///
+/// ```
+/// use tracy_client::span;
+/// # let _client = tracy_client::Client::start();
+/// let _span = span!("some red span", 0, 0xFF0000FF);
+/// ```
 #[macro_export]
 macro_rules! span {
     () => {
@@ -274,4 +562,84 @@ macro_rules! span {
             .expect("span! without a running Client")
             .span(location, $callstack_depth)
     }};
+    ($name: expr, $callstack_depth: expr, $rgba: expr) => {{
+        let location = $crate::span_location!($name, $rgba);
+        $crate::Client::running()
+            .expect("span! without a running Client")
+            .span(location, $callstack_depth)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "enable")]
+    fn raw_span_round_trip() {
+        let client = Client::start();
+        let span = client.span(span_location!("raw round trip"), 0);
+        let (id, active) = (span.zone.id, span.zone.active);
+        let span = unsafe { Span::from_raw(span.into_raw()) };
+        assert_eq!((span.zone.id, span.zone.active), (id, active));
+    }
+
+    #[test]
+    fn debug_text_buffer_is_cleared() {
+        assert_eq!(with_debug_text(&"first", str::to_owned), "\"first\"");
+        assert_eq!(with_debug_text(&2, str::to_owned), "2");
+        assert_eq!(with_debug_text(&[3, 4], str::to_owned), "[3, 4]");
+    }
+
+    #[test]
+    fn debug_text_is_reentrant() {
+        struct Nested;
+        impl std::fmt::Debug for Nested {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let inner = with_debug_text(&"inner", str::to_owned);
+                write!(f, "outer({inner})")
+            }
+        }
+        assert_eq!(with_debug_text(&Nested, str::to_owned), "outer(\"inner\")");
+    }
+
+    #[test]
+    fn text_within_limit_is_not_truncated() {
+        let text = "a".repeat(8 * 1024);
+        assert_eq!(truncate_to_length(&text, MAX_TEXT_LENGTH), text);
+        let text = "a".repeat(MAX_TEXT_LENGTH);
+        assert_eq!(truncate_to_length(&text, MAX_TEXT_LENGTH), text);
+    }
+
+    #[test]
+    fn text_over_limit_is_truncated_at_char_boundary() {
+        let text = "a".repeat(MAX_TEXT_LENGTH + 10);
+        assert_eq!(
+            truncate_to_length(&text, MAX_TEXT_LENGTH).len(),
+            MAX_TEXT_LENGTH
+        );
+        // 3-byte characters do not evenly divide the limit.
+        let text = "€".repeat(MAX_TEXT_LENGTH);
+        let truncated = truncate_to_length(&text, MAX_TEXT_LENGTH);
+        assert_eq!(truncated.len(), MAX_TEXT_LENGTH - MAX_TEXT_LENGTH % 3);
+        assert!(truncated.chars().all(|c| c == '€'));
+    }
+
+    #[test]
+    fn location_cache() {
+        let cache = SpanLocationCache::new("tests::location_cache", file!(), line!());
+        let ping = cache.location("ping");
+        assert!(std::ptr::eq(ping, cache.location("ping")));
+        // Locations are zero-sized without the `enable` feature, and thus all share an address.
+        #[cfg(feature = "enable")]
+        assert!(!std::ptr::eq(ping, cache.location("pong")));
+        assert!(std::ptr::eq(ping, cache.location(&String::from("ping"))));
+    }
+
+    #[test]
+    #[cfg(feature = "enable")]
+    fn location_color() {
+        assert_eq!(span_location!("uncolored").data.color, 0);
+        assert_eq!(span_location!("colored", 0xFF8000FF).data.color, 0xFF8000);
+    }
 }
@@ -11,10 +11,17 @@ impl Client {
     /// The underlying client implementation will be started up only if it wasn't already running
     /// yet.
     ///
-    /// Note that when the `manual-lifetime` feature is used, it is a responsibility of the user
-    /// to stop `tracy` using the [`sys::___tracy_shutdown_profiler`] function. Keep in mind that
-    /// at the time this function is called there can be no other invocations to the tracy
-    /// profiler, even from other threads (or you may get a crash!)
+    /// When the `manual-lifetime` feature is enabled (corresponding to the
+    /// `TRACY_MANUAL_LIFETIME` define), Tracy is not started when the program starts and the first
+    /// call to this function is the point at which profiling starts. Until then
+    /// [`Client::running`] returns `None`.
+    ///
+    /// The client is not shut down when the `Client` values are dropped: Tracy must be shut down
+    /// on the thread that started it, and only once it is guaranteed that nothing will invoke the
+    /// profiler anymore, which a reference count of the cheap handles cannot ensure (e.g. the
+    /// [`ProfiledAllocator`](crate::ProfiledAllocator) obtains a short-lived handle for every
+    /// allocation). Instead, the client may be started with `Client::start_owned`, which shuts
+    /// the client down once the last of the returned owners is dropped.
     ///
     /// # Example
     ///
@@ -33,6 +40,30 @@ impl Client {
         return Self(());
     }
 
+    /// Start the client, but only if `enabled` is `true`.
+    ///
+    /// This provides a single place to gate the profiling (and with it, the network exposure of
+    /// the profiled data) with a runtime flag, such as an environment variable. Returns `None`
+    /// without starting anything if `enabled` is `false`.
+    ///
+    /// Note that only with the `manual-lifetime` feature does this actually control whether Tracy
+    /// runs: by default Tracy starts up, and begins listening for and broadcasting to profilers,
+    /// before `main` regardless of whether this function is ever called. Without that feature
+    /// this merely withholds the `Client` handle from the code gated by the flag.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let profiling = std::env::var_os("MY_APP_PROFILE").is_some();
+    /// if let Some(client) = tracy_client::Client::try_start_if(profiling) {
+    ///     client.message("profiling enabled", 0);
+    /// }
+    /// ```
+    #[must_use]
+    pub fn try_start_if(enabled: bool) -> Option<Self> {
+        enabled.then(Self::start)
+    }
+
     /// Obtain a client handle, but only if the client is already running.
     #[must_use]
     pub fn running() -> Option<Self> {
@@ -54,6 +85,185 @@ impl Client {
         return true; // The client is started in life-before-main (or upon first use in case of
                      // `delayed-init`
     }
+
+    /// Is a profiler application currently connected to this client?
+    ///
+    /// This is useful to skip expensive instrumentation (such as formatting of debug strings or
+    /// capturing frame images) while nobody is around to observe the data. With the `ondemand`
+    /// feature this returns `false` until a profiler connects.
+    ///
+    /// Always returns `false` if the `enable` feature is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn expensive_description() -> String { String::new() }
+    /// let client = tracy_client::Client::start();
+    /// if client.is_connected() {
+    ///     client.message(&expensive_description(), 0);
+    /// }
+    /// ```
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        #[cfg(feature = "enable")]
+        unsafe {
+            // SAFE: We have a running client.
+            sys::___tracy_connected() != 0
+        }
+        #[cfg(not(feature = "enable"))]
+        false
+    }
+
+    /// Block until a profiler application connects to this client.
+    ///
+    /// Waits at most for `timeout`, or indefinitely if `timeout` is `None`. Returns whether a
+    /// profiler is connected. This is useful with the `ondemand` feature to e.g. hold off a
+    /// benchmark until the profiler is ready to record it.
+    ///
+    /// Always returns `false` immediately if the `enable` feature is disabled.
+    ///
+    /// Note that a connected profiler may not have received all of the data collected so far, and
+    /// Tracy provides no means to wait for that on demand (see [`Client::flush`]). Data that has
+    /// not been sent by the time the program exits is lost, unless the `flush-on-exit` feature is
+    /// enabled, in which case the program waits at exit until a profiler has connected and read
+    /// out all of the data. Short lived programs, such as command line tools, should enable that
+    /// feature instead of relying on this method.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// let client = tracy_client::Client::start();
+    /// if !client.wait_for_connection(Some(Duration::from_millis(10))) {
+    ///     eprintln!("profiling without a connected profiler");
+    /// }
+    /// ```
+    pub fn wait_for_connection(&self, timeout: Option<std::time::Duration>) -> bool {
+        #[cfg(feature = "enable")]
+        {
+            let start = std::time::Instant::now();
+            loop {
+                if self.is_connected() {
+                    return true;
+                }
+                let elapsed = start.elapsed();
+                let poll = std::time::Duration::from_millis(10);
+                let poll = match timeout {
+                    Some(timeout) if elapsed >= timeout => return false,
+                    Some(timeout) => poll.min(timeout - elapsed),
+                    None => poll,
+                };
+                std::thread::sleep(poll);
+            }
+        }
+        #[cfg(not(feature = "enable"))]
+        false
+    }
+
+    /// Wait for a short, fixed time to give the client an opportunity to send the data collected so
+    /// far to a connected profiler.
+    ///
+    /// This does not actually flush anything: Tracy provides no means to flush its queues on
+    /// demand, nor to observe whether they have been drained. Instead, if a profiler is connected,
+    /// the current thread sleeps for 30 milliseconds, a few intervals of the thread which sends
+    /// the data. That is often, but not always, long enough for the data to be sent, and there is
+    /// no guarantee that any of it reaches the profiler by the time this returns. Returns
+    /// immediately if no profiler is connected, so this does not help with a profiler that
+    /// connects late; the `flush-on-exit` feature does.
+    ///
+    /// Returns whether a profiler was connected. Always returns `false` immediately if the
+    /// `enable` feature is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let client = tracy_client::Client::start();
+    /// client.message("done", 0);
+    /// client.flush();
+    /// ```
+    pub fn flush(&self) -> bool {
+        if !self.is_connected() {
+            return false;
+        }
+        // The thread sending the data sleeps for 10 milliseconds whenever it runs out of data.
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        self.is_connected()
+    }
+}
+
+/// Shutting down of the client with the `manual-lifetime` feature.
+#[cfg(feature = "manual-lifetime")]
+impl Client {
+    /// Start the client, returning an owner which keeps it running.
+    ///
+    /// The owners are reference counted, and the client is shut down once the last of them is
+    /// dropped, after which [`Client::running`] returns `None` until the client is started again.
+    /// The `Client` handles obtained in other ways do not keep the client running.
+    ///
+    /// # Safety
+    ///
+    /// Once the last owner is dropped, nothing may invoke the instrumentation until the client is
+    /// started again, even from other threads and through the `Client` handles obtained earlier
+    /// (e.g. those held by a [`ProfiledAllocator`](crate::ProfiledAllocator) or a layer of the
+    /// `tracing-tracy` crate). Tracy must additionally be shut down on the thread it was started
+    /// on, so the last owner must be dropped on the thread that started the client.
+    ///
+    /// A layer of the `tracing-tracy` crate checks whether the client is running before reporting
+    /// anything, so it stops reporting once the client is shut down and resumes once it is started
+    /// again. That check is not synchronized with the shutdown though, so no spans or events may
+    /// be recorded on other threads while the last owner is being dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // fn main() {
+    ///     // SAFE: the profiler is not used once `_owner` is dropped at the end of `main`.
+    ///     let _owner = unsafe { tracy_client::Client::start_owned() };
+    ///     // ...
+    /// // }
+    /// ```
+    pub unsafe fn start_owned() -> ClientOwner {
+        #[cfg(feature = "enable")]
+        let client = manual_lifetime::acquire_owner();
+        #[cfg(not(feature = "enable"))]
+        let client = Self(());
+        ClientOwner {
+            client,
+            _not_send: std::marker::PhantomData,
+        }
+    }
+}
+
+/// An owner of the Tracy client, which shuts the client down once the last owner is dropped.
+///
+/// Obtained with [`Client::start_owned`], and dereferences to a [`Client`].
+#[cfg(feature = "manual-lifetime")]
+#[must_use = "the client is shut down once the last owner is dropped"]
+pub struct ClientOwner {
+    client: Client,
+    /// Tracy must be shut down on the thread that started it.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+#[cfg(feature = "manual-lifetime")]
+impl std::ops::Deref for ClientOwner {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+#[cfg(feature = "manual-lifetime")]
+impl Drop for ClientOwner {
+    fn drop(&mut self) {
+        #[cfg(feature = "enable")]
+        unsafe {
+            // SAFE: the caller of `start_owned` guaranteed that the profiler is not used anymore
+            // once the last owner is dropped, on the thread that started it.
+            manual_lifetime::release_owner();
+        }
+    }
 }
 
 impl Clone for Client {
@@ -68,6 +278,7 @@ impl Clone for Client {
 #[cfg(all(feature = "enable", feature = "manual-lifetime"))]
 mod manual_lifetime {
     use std::sync::atomic::Ordering;
+    use std::sync::PoisonError;
     /// Enabling `Tracy` when it is already enabled, or Disabling when it is already disabled will
     /// cause applications to crash. I personally think it would be better if this was a sort-of
     /// reference counted kind-of thing so you could enable as many times as you wish and disable
@@ -91,8 +302,9 @@ mod manual_lifetime {
     /// that would be to run a separate thread that would be dedicated entirely to just starting up and
     /// shutting down the profiler.
     ///
-    /// All that seems like a major pain to implement, and so we’ll punt on disabling entirely until
-    /// somebody comes with a good use-case warranting that sort of complexity.
+    /// All that seems like a major pain to implement, and so only the explicitly reference counted
+    /// owners from `Client::start_owned` disable the client, with the caller guaranteeing that the
+    /// profiler is not used anymore, on the right thread, once the last of them is dropped.
     #[cfg(not(loom))]
     static CLIENT_STATE: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
     #[cfg(loom)]
@@ -105,6 +317,14 @@ mod manual_lifetime {
     const STATE_DISABLED: usize = 0;
     const STATE_ENABLING: usize = STATE_DISABLED + STATE_STEP;
     const STATE_ENABLED: usize = STATE_ENABLING + STATE_STEP;
+    const STATE_DISABLING: usize = STATE_ENABLED + STATE_STEP;
+
+    /// The number of the living `ClientOwner`s.
+    ///
+    /// The lock is held while the owners start and shut down the client, so that a new owner
+    /// cannot observe the client running just before the last of the previous owners shuts it
+    /// down.
+    static OWNERS: std::sync::Mutex<usize> = std::sync::Mutex::new(0);
 
     #[inline(always)]
     fn spin_loop() {
@@ -154,13 +374,43 @@ mod manual_lifetime {
                         }
                     }
                 }
+                STATE_DISABLING => {
+                    // The client is started anew once the last owner has shut it down.
+                    while CLIENT_STATE.load(Ordering::Acquire) == STATE_DISABLING {
+                        spin_loop();
+                    }
+                    old_state = CLIENT_STATE.load(Ordering::Relaxed);
+                }
                 _ => unreachable!(),
             }
         }
     }
 
+    pub(super) fn acquire_owner() -> super::Client {
+        let mut owners = OWNERS.lock().unwrap_or_else(PoisonError::into_inner);
+        *owners += 1;
+        start()
+    }
+
+    /// # Safety
+    ///
+    /// See `Client::start_owned`.
+    pub(super) unsafe fn release_owner() {
+        let mut owners = OWNERS.lock().unwrap_or_else(PoisonError::into_inner);
+        *owners -= 1;
+        if *owners == 0 {
+            CLIENT_STATE.store(STATE_DISABLING, Ordering::Relaxed);
+            unsafe {
+                // SAFE: The client was started by the owners, and the caller guarantees that
+                // nothing invokes the profiler anymore.
+                let () = sys::___tracy_shutdown_profiler();
+            }
+            CLIENT_STATE.store(STATE_DISABLED, Ordering::Release);
+        }
+    }
+
     pub(super) fn is_running() -> bool {
-        return CLIENT_STATE.load(Ordering::Relaxed) == STATE_ENABLED;
+        CLIENT_STATE.load(Ordering::Relaxed) == STATE_ENABLED
     }
 
     #[cfg(test)]
@@ -172,6 +422,7 @@ mod manual_lifetime {
             assert_eq!(0, STATE_DISABLED);
             assert_eq!(STATE_DISABLED.wrapping_add(STATE_STEP), STATE_ENABLING);
             assert_eq!(STATE_ENABLING.wrapping_add(STATE_STEP), STATE_ENABLED);
+            assert_eq!(STATE_ENABLED.wrapping_add(STATE_STEP), STATE_DISABLING);
         }
     }
 }
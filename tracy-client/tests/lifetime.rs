@@ -0,0 +1,30 @@
+//! Starts and shuts down the client with `Client::start_owned`.
+//!
+//! This is a separate test binary, as nothing else may use the profiler once it is shut down, and
+//! the other tests do so from many threads and through a profiled global allocator.
+
+#[cfg(feature = "manual-lifetime")]
+fn owners() {
+    use tracy_client::Client;
+    for _ in 0..3 {
+        assert_eq!(Client::is_running(), cfg!(not(feature = "enable")));
+        // SAFE: nothing else uses the profiler in this test.
+        let first = unsafe { Client::start_owned() };
+        let second = unsafe { Client::start_owned() };
+        assert!(Client::is_running());
+        first.message("started by an owner", 0);
+        drop(first);
+        assert!(
+            Client::is_running(),
+            "another owner keeps the client running"
+        );
+        second.message("still running", 0);
+        drop(second);
+        assert_eq!(Client::is_running(), cfg!(not(feature = "enable")));
+    }
+}
+
+fn main() {
+    #[cfg(feature = "manual-lifetime")]
+    owners();
+}
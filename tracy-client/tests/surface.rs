@@ -0,0 +1,152 @@
+//! Calls every public function of the crate, so that the API available with the `enable` feature
+//! disabled is guaranteed to stay identical to the API with the feature enabled.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::time::Duration;
+use tracy_client::*;
+
+fn names() {
+    let _ = FrameName::new_leak(String::from("surface frame"));
+    let _ = PlotName::new_leak(String::from("surface plot"));
+    let _ = FiberName::new_leak(String::from("surface fiber"));
+    let _ = PoolName::new_leak(String::from("surface pool"));
+    let _ = Color::rgba(0, 0, 0, 0).to_rgba();
+    let _ = Color::from((0, 0, 0)).to_tracy_u32();
+    let _ = SpanLocationCache::new("surface::names", file!(), line!()).location("surface");
+    let _ = SpanLocation::new_leak(
+        Some(String::from("surface")),
+        String::from("surface::names"),
+        String::from(file!()),
+        line!(),
+    );
+}
+
+fn client() {
+    assert!(Client::is_running());
+    assert!(Client::try_start_if(false).is_none());
+    let _ = feature_summary();
+    let _ = protocol_version();
+    let _ = active_collection_modes();
+    let client = Client::running().expect("the client is running");
+    // Not called, as the owner would shut down the client still used by the other functions.
+    #[cfg(feature = "manual-lifetime")]
+    let _: fn() -> ClientOwner = || unsafe { Client::start_owned() };
+    let _ = client.is_connected();
+    let _ = client.wait_for_connection(Some(Duration::ZERO));
+    let _ = client.flush();
+    client.message("surface", 0);
+    client.message_with_callstack("surface", 0);
+    client.color_message("surface", 0xFF0000FF, 0);
+    client.color_message_with_callstack("surface", 0xFF0000FF, 0);
+    client.app_info("surface");
+    client.set_default_stack_depth(client.default_stack_depth());
+    client.set_thread_name("surface");
+    client.set_thread_name_with_hint("surface", 0);
+    client.plot(plot_name!("surface"), 0.0);
+    client.plot_if_changed(plot_name!("surface"), 0.0);
+    client.plot_int(plot_name!("surface"), 0);
+    for format in [
+        PlotFormat::Number,
+        PlotFormat::Memory,
+        PlotFormat::Percentage,
+        PlotFormat::Watt,
+    ] {
+        client.set_plot_format(plot_name!("surface"), format);
+    }
+    client.memory_alloc(Some(pool_name!("surface")), 1, 1, 0);
+    client.memory_free(Some(pool_name!("surface")), 1, 0);
+    let previous = client.set_thread_memory_pool(Some(pool_name!("surface")));
+    let _ = client.thread_memory_pool();
+    client.set_thread_memory_pool(previous);
+    message!("surface");
+    color_message!("surface", 0xFF0000FF);
+    color_message!("surface", Color::RED);
+    set_thread_name!("surface");
+    plot!("surface", 1.0);
+}
+
+fn spans() {
+    let client = Client::start();
+    let span = client.clone().span(span_location!("surface"), 0);
+    span.emit_value(0);
+    span.emit_text("surface");
+    span.emit_debug(&"surface");
+    span.emit_color(0xFF0000FF);
+    span.emit_color(Color::rgb(0xFF, 0, 0));
+    span.emit_name("surface");
+    drop(unsafe { Span::from_raw(span.into_raw()) });
+    let _ = client.span_alloc(Some("surface"), "spans", file!(), line!(), 0);
+    let _ = span!("surface", 0);
+}
+
+fn frames() {
+    let client = Client::start();
+    client.frame_mark();
+    client.secondary_frame_mark(frame_name!("surface"));
+    drop(client.non_continuous_frame(frame_name!("surface")));
+    client.frame_start(frame_name!("surface manual"));
+    client.frame_end(frame_name!("surface manual"));
+    client.frame_image(&[0; 4 * 4 * 4], 4, 4, 0, false);
+    frame_mark();
+    secondary_frame_mark!("surface");
+    drop(non_continuous_frame!("surface"));
+}
+
+fn fibers() {
+    let client = Client::start();
+    client.fiber_enter(fiber_name!("surface"));
+    client.fiber_leave();
+    drop(client.fiber(fiber_name!("surface")));
+}
+
+fn gpu() {
+    let client = Client::start();
+    let context = client
+        .clone()
+        .new_gpu_context(None, GpuContextType::Invalid, 0, 1.0)
+        .unwrap();
+    let mut span = context.span(span_location!("surface")).unwrap();
+    span.end_zone();
+    span.upload_timestamp(0, 0);
+    let mut span = context
+        .span_alloc("surface", "gpu", file!(), line!())
+        .unwrap();
+    span.end_zone();
+    span.upload_timestamp(0, 0);
+    let context = client
+        .new_calibrated_gpu_context(Some("surface"), GpuContextType::Invalid, 0, 1.0)
+        .unwrap();
+    context.calibrate(0, 0);
+}
+
+fn allocators() {
+    static ALLOCATORS: [ProfiledAllocator<System>; 5] = [
+        ProfiledAllocator::new(System, 0),
+        ProfiledAllocator::new_named(System, 0, pool_name!("surface")),
+        ProfiledAllocator::with_callstack_threshold(System, 0, 0),
+        ProfiledAllocator::with_heap_plot(System, 0, plot_name!("surface heap")),
+        ProfiledAllocator::with_thread_pools(System, 0),
+    ];
+    let layout = Layout::new::<u64>();
+    for allocator in &ALLOCATORS {
+        unsafe {
+            let ptr = allocator.alloc_zeroed(layout);
+            let ptr = allocator.realloc(ptr, layout, 16);
+            allocator.dealloc(ptr, Layout::from_size_align(16, 8).unwrap());
+        }
+    }
+}
+
+fn main() {
+    #[cfg(not(loom))]
+    {
+        let _client = Client::start();
+        names();
+        client();
+        spans();
+        frames();
+        fibers();
+        gpu();
+        allocators();
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
+
 use std::time::Duration;
 
 use tracy_client::*;
@@ -11,6 +13,10 @@ fn basic_zone() {
     let span = client.span(span_location!("basic_zone"), 100);
     span.emit_value(42);
     span.emit_text("some text");
+    span.emit_debug(&("some", ["debug", "text"]));
+    span.emit_debug(&42);
+    span.emit_text(&"long text ".repeat(1024));
+    span.emit_text(&"too long text ".repeat(8 * 1024));
     for i in 322..420 {
         span.emit_value(i);
     }
@@ -20,10 +26,54 @@ fn alloc_zone() {
     let client = Client::start();
     let span = client.span_alloc(Some("alloc_zone"), "alloc_zone", file!(), line!(), 100);
     span.emit_value(42);
-    span.emit_color(0x00FF0000);
+    span.emit_color(0xFF0000FF);
     span.emit_text("some text");
 }
 
+fn runtime_location() {
+    let client = Client::start();
+    let span = {
+        let name = format!("runtime span {}", 42);
+        let function = String::from("tests::runtime_location");
+        let file = String::from(file!());
+        client.span_alloc(Some(&name), &function, &file, line!(), 0)
+    };
+    span.emit_text("the strings above are gone by now");
+    drop(span);
+}
+
+fn location_color() {
+    let client = Client::start();
+    let _ = client.span(span_location!("red location", 0xFF0000FF), 0);
+    let _ = span!("green span", 0, 0x00FF00FF);
+}
+
+fn raw_span() {
+    let client = Client::start();
+    let begin = || {
+        client
+            .clone()
+            .span(span_location!("raw span"), 0)
+            .into_raw()
+    };
+    let end = |raw| drop(unsafe { Span::from_raw(raw) });
+    let outer = begin();
+    let inner = begin();
+    end(inner);
+    end(outer);
+}
+
+fn runtime_name_and_color() {
+    let client = Client::start();
+    let span = client.span(span_location!("placeholder name"), 0);
+    span.emit_name("dispatched message");
+    span.emit_color(0xFF0000FF);
+    let span = span!();
+    span.emit_name(&format!("runtime name {}", 42));
+    span.emit_color(0x00FF00FF);
+    span.emit_color(0x0000FFFF);
+}
+
 fn finish_frameset() {
     let client = Client::start();
     for _ in 0..10 {
@@ -40,6 +90,18 @@ fn finish_secondary_frameset() {
     secondary_frame_mark!("secondary frame macro");
 }
 
+fn independent_secondary_framesets() {
+    const PHYSICS: FrameName = frame_name!("physics");
+    const RENDER: FrameName = frame_name!("render");
+    let client = Client::start();
+    for tick in 0..12 {
+        client.secondary_frame_mark(PHYSICS);
+        if tick % 3 == 0 {
+            client.secondary_frame_mark(RENDER);
+        }
+    }
+}
+
 fn non_continuous_frameset() {
     const NON_CONTINUOUS: FrameName = frame_name!("non continuous");
     let client = Client::start();
@@ -47,6 +109,54 @@ fn non_continuous_frameset() {
     let _ = non_continuous_frame!("non continuous macro");
 }
 
+fn non_continuous_frame_unwinding() {
+    const STARTUP: FrameName = frame_name!("startup");
+    let client = Client::start();
+    let result = std::panic::catch_unwind(|| {
+        let _frame = client.non_continuous_frame(STARTUP);
+        panic!("startup failed");
+    });
+    assert!(result.is_err());
+    // Had the frame not been ended during unwinding, this would overlap with it.
+    let _frame = client.non_continuous_frame(STARTUP);
+}
+
+fn nested_non_continuous_frames() {
+    const LOAD: FrameName = frame_name!("load");
+    const RENDER: FrameName = frame_name!("render");
+    let client = Client::start();
+    {
+        let _load = client.non_continuous_frame(LOAD);
+        let _render = client.non_continuous_frame(RENDER);
+    }
+    client.frame_start(LOAD);
+    client.frame_start(RENDER);
+    client.frame_end(RENDER);
+    client.frame_end(LOAD);
+}
+
+fn frame_image() {
+    let client = Client::start();
+    let image = vec![0x7Fu8; 64 * 32 * 4];
+    client.frame_image(&image, 64, 32, 0, false);
+    client.frame_image(&image, 32, 64, 1, true);
+    let result = std::panic::catch_unwind(|| client.frame_image(&image[1..], 64, 32, 0, false));
+    assert!(result.is_err(), "mismatched buffer length must be rejected");
+}
+
+fn fibers() {
+    let client = Client::start();
+    client.fiber_enter(fiber_name!("fiber 1"));
+    let _ = span!("in fiber 1");
+    client.fiber_leave();
+    {
+        let _fiber = client.fiber(fiber_name!("fiber 2"));
+        let _ = span!("in fiber 2");
+    }
+    client.fiber_enter(fiber_name!("fiber 1"));
+    client.fiber_leave();
+}
+
 fn plot_something() {
     static TEMPERATURE: PlotName = plot_name!("temperature");
     let client = Client::start();
@@ -55,6 +165,16 @@ fn plot_something() {
     }
 
     plot!("temperature", 42.0);
+
+    static RESIDENT: PlotName = plot_name!("resident memory");
+    client.set_plot_format(RESIDENT, PlotFormat::Memory);
+    client.plot_int(RESIDENT, i64::MAX);
+    client.plot_int(RESIDENT, 0);
+
+    static CONNECTIONS: PlotName = plot_name!("connections");
+    for i in 0..100 {
+        client.plot_if_changed(CONNECTIONS, f64::from(i / 10));
+    }
 }
 
 fn allocations() {
@@ -64,6 +184,65 @@ fn allocations() {
     }
 }
 
+fn named_pools() {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    static TEXTURES: ProfiledAllocator<System> =
+        ProfiledAllocator::new_named(System, 0, pool_name!("textures"));
+    static SCRATCH: ProfiledAllocator<System> =
+        ProfiledAllocator::new_named(System, 100, pool_name!("scratch"));
+    let _client = Client::start();
+    let layout = Layout::from_size_align(1024, 8).unwrap();
+    unsafe {
+        let texture = TEXTURES.alloc(layout);
+        let scratch = SCRATCH.alloc_zeroed(layout);
+        let texture = TEXTURES.realloc(texture, layout, 4096);
+        SCRATCH.dealloc(scratch, layout);
+        TEXTURES.dealloc(texture, Layout::from_size_align(4096, 8).unwrap());
+    }
+}
+
+fn manual_memory() {
+    const ARENA: PoolName = pool_name!("arena");
+    let client = Client::start();
+    for offset in 0..4 {
+        client.memory_alloc(Some(ARENA), 0x1000 + offset * 64, 64, 0);
+    }
+    client.memory_alloc(None, 0x2000, 4096, 16);
+    for offset in 0..4 {
+        client.memory_free(Some(ARENA), 0x1000 + offset * 64, 0);
+    }
+    client.memory_free(None, 0x2000, 16);
+}
+
+fn callstack_threshold() {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    static LARGE: ProfiledAllocator<System> =
+        ProfiledAllocator::with_callstack_threshold(System, 16, 4096);
+    let _client = Client::start();
+    let small = Layout::from_size_align(64, 8).unwrap();
+    let large = Layout::from_size_align(64 * 1024, 8).unwrap();
+    unsafe {
+        let without_callstack = LARGE.alloc(small);
+        let with_callstack = LARGE.alloc(large);
+        LARGE.dealloc(without_callstack, small);
+        LARGE.dealloc(with_callstack, large);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+fn collection_allocator() {
+    let _client = Client::start();
+    let allocator = ProfiledAllocator::new_named(std::alloc::Global, 0, pool_name!("collection"));
+    let mut numbers = Vec::new_in(&allocator);
+    for i in 0..1000 {
+        numbers.push(i);
+    }
+    numbers.truncate(10);
+    numbers.shrink_to_fit();
+    let zeroed = Box::<[u64], _>::new_zeroed_slice_in(64, &allocator);
+    drop((numbers, zeroed));
+}
+
 fn fib(i: u16) -> u64 {
     let span = span!();
     span.emit_text(&format!("fib({i})"));
@@ -80,8 +259,63 @@ fn message() {
     let client = Client::start();
     client.message("test message", 100);
     client.message("test message without stack", 0);
+    message!("test message macro");
+    message!(&format!("test message macro with {} frames", 100), 100);
+    color_message!("test color message macro", 0xFF0000FF);
+    client.message_with_callstack("test message with callstack", 16);
+    client.message_with_callstack("test message without callstack", 0);
+    message!("test message macro without callstack", 0);
+    client.color_message_with_callstack("test color message with callstack", 0xFF0000FF, 16);
+    client.color_message_with_callstack("test color message without callstack", 0xFF0000FF, 0);
+    color_message!("test color message macro without callstack", 0xFF0000FF, 0);
+    client.set_default_stack_depth(1000);
+    #[cfg(windows)]
+    assert_eq!(client.default_stack_depth(), 62);
+    #[cfg(not(windows))]
+    assert_eq!(client.default_stack_depth(), 1000);
+    client.set_default_stack_depth(16);
+    message!("test message macro with the default depth");
+    color_message!(
+        "test color message macro with the default depth",
+        0x00FF00FF
+    );
+    color_message!(
+        "test color message macro with explicit depth",
+        0x0000FFFF,
+        0
+    );
+    client.set_default_stack_depth(0);
 }
 
+fn app_info() {
+    let client = Client::start();
+    client.app_info("tracy-client tests\nversion: 0.0.0\ncommit: 0000000");
+}
+
+fn try_start_if() {
+    assert!(Client::try_start_if(false).is_none());
+    assert!(Client::try_start_if(true).is_some());
+}
+
+fn is_connected() {
+    let client = Client::start();
+    // Nothing is listening while the tests run, so this is merely a smoke test.
+    let _ = client.is_connected();
+    #[cfg(not(feature = "enable"))]
+    assert!(!client.is_connected());
+    let start = std::time::Instant::now();
+    let _ = client.wait_for_connection(Some(Duration::ZERO));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    let _ = client.wait_for_connection(Some(Duration::from_millis(25)));
+    // Without a profiler flushing returns right away, however many times it is called.
+    let start = std::time::Instant::now();
+    for _ in 0..100 {
+        assert!(!client.flush());
+    }
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[allow(clippy::drop_non_drop)] // Dropping the handle on another thread is the point here.
 fn tls_confusion() {
     let client = Client::start();
     let t1 = std::thread::spawn(move || {
@@ -92,8 +326,11 @@ fn tls_confusion() {
 }
 
 fn set_thread_name() {
-    let _client = Client::start();
+    let client = Client::start();
     set_thread_name!("test thread");
+    std::thread::spawn(move || client.set_thread_name_with_hint("pool thread", 1))
+        .join()
+        .unwrap();
 }
 
 fn nameless_span() {
@@ -130,17 +367,51 @@ fn gpu() {
     span2.upload_timestamp(120_000, 130_000);
 }
 
+fn gpu_calibration() {
+    let client = Client::start();
+    let gpu_context = client
+        .new_calibrated_gpu_context(Some("Calibrated"), GpuContextType::Vulkan, 1_000, 1.0)
+        .unwrap();
+    for i in 1..=3 {
+        let mut span = gpu_context.span(span_location!("calibrated")).unwrap();
+        span.end_zone();
+        span.upload_timestamp(i * 10_000, i * 10_000 + 5_000);
+        gpu_context.calibrate(i * 20_000, 20_000);
+    }
+    let uncalibrated = Client::start()
+        .new_gpu_context(None, GpuContextType::Vulkan, 1_000, 1.0)
+        .unwrap();
+    uncalibrated.calibrate(20_000, 20_000);
+}
+
 fn main() {
     #[cfg(not(loom))]
     {
         basic_zone();
         alloc_zone();
+        runtime_location();
+        location_color();
+        raw_span();
+        runtime_name_and_color();
         finish_frameset();
         finish_secondary_frameset();
+        independent_secondary_framesets();
         non_continuous_frameset();
+        nested_non_continuous_frames();
+        non_continuous_frame_unwinding();
+        frame_image();
+        fibers();
         plot_something();
         message();
+        app_info();
+        try_start_if();
+        is_connected();
         allocations();
+        named_pools();
+        manual_memory();
+        callstack_threshold();
+        #[cfg(feature = "allocator-api")]
+        collection_allocator();
         tls_confusion();
         nameless_span();
         let thread = std::thread::spawn(|| {
@@ -150,6 +421,7 @@ fn main() {
         thread.join().unwrap();
         set_thread_name();
         gpu();
+        gpu_calibration();
         // Sleep to give time to the client to send the data to the profiler.
         std::thread::sleep(Duration::from_secs(5));
     }